@@ -39,10 +39,15 @@ use syn::{
 /// Attributes are encapsulated within `#[config(...)]` or `#[config_it(...)]`.
 ///
 /// - `alias = "<name>"`: Assign an alias to the field.
+/// - `previous_names = ["<name>"...]`: Legacy names of the field, which are read on import when
+///   the current name is missing. Only the current name is written on export, as long as the
+///   group is alive; categories without a live group are exported as imported, since the storage
+///   doesn't know their template. Register a migration to rename keys of those as well.
 /// - `default = <expr>` or `default_expr = "<expr>"`: Define a default value for the field.
 /// - `admin | admin_write | admin_read`: Restrict access to the field for non-admin users.
 ///
-/// - `min = <expr>`, `max = <expr>`, `one_of = [<expr>...]`: Apply constraints to the field.
+/// - `min = <expr>`, `max = <expr>`, `one_of = [<expr>...]`: Apply constraints to the field. With
+///   `jsonschema` feature, they're also written into the schema.
 /// - `validate_with = "<function_name>"`: Specify a validation function for the field with the
 ///   signature `fn(&mut T) -> Result<Validation, impl Into<Cow<'static, str>>>`.
 ///
@@ -53,12 +58,22 @@ use syn::{
 /// - `env = "<literal>"` or `env_once = "<literal>"`: Set the default value from an environment
 ///   variable.
 /// - `transient | no_export | no_import`: Prevent field export/import.
+/// - `deprecated = "<note>"`: Mark the field as deprecated. The note is recorded in the metadata
+///   and the schema, and a warning is logged whenever an imported archive sets the field.
 /// - `editor = <ident>`: Define an editor hint for the field. See
 ///   [`config_it::shared::meta::MetadataEditorHint`](https://docs.rs/config-it/latest/config_it/shared/meta/enum.MetadataEditorHint.html)
 ///   - e.g. Specify expression as `editor = ColorRgba255`, `editor = Code("rust".into())`, etc.
 /// - `hidden` or `hidden_non_admin`: Make a field invisible in the editor or only to non-admin
 ///   users, respectively.
 ///
+/// # Template Attributes
+///
+/// The struct itself accepts `#[config(validate = "<function_name>")]`, which specifies a
+/// validation function over the whole template with the signature `fn(&mut Self) ->
+/// Result<Validation, impl Into<Cow<'static, str>>>`. It receives the candidate state of the group
+/// on every commit and import, which makes it possible to check constraints between fields. The
+/// candidate may be adjusted in place, and rejecting it leaves the group untouched.
+///
 /// # Interacting with non-config-it Types
 ///
 /// For non-configuration types that lack a `Default` trait, the `#[non_config_default_expr =
@@ -68,8 +83,7 @@ use syn::{
 #[proc_macro_derive(Template, attributes(config_it, config, non_config_default_expr))]
 pub fn derive_collect_fn(item: LangTokenStream) -> LangTokenStream {
     let tokens = TokenStream::from(item);
-    let Ok(syn::ItemStruct { attrs: _, ident, fields, .. }) =
-        syn::parse2::<syn::ItemStruct>(tokens)
+    let Ok(syn::ItemStruct { attrs, ident, fields, .. }) = syn::parse2::<syn::ItemStruct>(tokens)
     else {
         proc_macro_error::abort_call_site!("expected struct")
     };
@@ -97,6 +111,14 @@ pub fn derive_collect_fn(item: LangTokenStream) -> LangTokenStream {
     } = gen;
     let n_props = fn_props.len();
 
+    let fn_validator = template_validator(attrs).map(|path| {
+        quote!(
+            fn validator__() -> Option<#this_crate::config::group::ValidateTemplateFn<Self>> {
+                Some(|this: &mut Self| #path(this).map_err(Into::into))
+            }
+        )
+    });
+
     quote!(
         #[allow(unused_parens)]
         #[allow(unused_imports)]
@@ -138,6 +160,8 @@ pub fn derive_collect_fn(item: LangTokenStream) -> LangTokenStream {
                         _ => panic!("Invalid index {}", index),
                     }
                 }
+
+                #fn_validator
             }
         };
     )
@@ -320,9 +344,13 @@ fn visit_fields(
                 writeonly,
                 env,
                 validate_with,
+                previous_names,
+                deprecated,
                 ..
             } = prop;
 
+            let previous_names = previous_names.map(|x| quote!(#x)).unwrap_or_else(|| quote!([]));
+
             let flags = [
                 readonly.then(|| quote!(MetaFlag::READONLY)),
                 writeonly.then(|| quote!(MetaFlag::WRITEONLY)),
@@ -359,10 +387,31 @@ fn visit_fields(
                 .unwrap_or_else(|| none.clone());
 
             let schema = cfg!(feature = "jsonschema").then(|| {
+                let edits = [
+                    deprecated.is_some().then(|| quote!(x.schema.metadata().deprecated = true;)),
+                    min.as_ref()
+                        .map(|v| quote!(x.schema.number().minimum = __schema_value(#v).as_f64();)),
+                    max.as_ref()
+                        .map(|v| quote!(x.schema.number().maximum = __schema_value(#v).as_f64();)),
+                    one_of.as_ref().map(|v| {
+                        quote!(x.schema.enum_values =
+                            Some(#v.into_iter().map(__schema_value).collect());)
+                    }),
+                ];
+                let edits = edits.iter().any(Option::is_some).then(|| {
+                    let edits = edits.iter().flatten();
+                    quote!(if let Some(x) = &mut schema { #(#edits)* })
+                });
+
                 quote! {
-                    __default_ref_ptr::<#field_ty>().get_schema()
+                    {
+                        let mut schema = __default_ref_ptr::<#field_ty>().get_schema();
+                        #edits
+                        schema
+                    }
                 }
             });
+            let deprecated = deprecated.map(|x| quote!(Some(#x))).unwrap_or_else(|| none.clone());
             let validation_function = {
                 let fn_min = min.map(|x| {
                     quote!(
@@ -439,6 +488,8 @@ fn visit_fields(
                             #editor_hint,
                             #doc_string,
                             #env,
+                            #deprecated,
+                            &#previous_names,
                             #schema // Comma is included
                         ),
                         /* vtable:*/ Box::leak(Box::new(__entity::MetadataVTableImpl {
@@ -467,6 +518,53 @@ fn visit_fields(
     }
 }
 
+/// Parses struct level `#[config(validate = "...")]` attribute.
+fn template_validator(attrs: Vec<Attribute>) -> Option<syn::ExprPath> {
+    let mut validator = None;
+
+    for Attribute { meta, .. } in attrs {
+        if !["config", "config_it"].into_iter().any(|x| meta.path().is_ident(x)) {
+            continue;
+        }
+
+        let Meta::List(list) = meta else {
+            emit_error!(meta, "Expected `#[config(...)]`");
+            continue;
+        };
+
+        let span = list.span();
+        let Ok(parsed) = list.parse_args_with(<Punctuated<syn::Meta, Token![,]>>::parse_terminated)
+        else {
+            emit_error!(span, "Expected valid list of arguments");
+            continue;
+        };
+
+        for arg in parsed {
+            let Meta::NameValue(syn::MetaNameValue { value, path, .. }) = arg else {
+                emit_error!(arg, "Unknown attribute");
+                continue;
+            };
+
+            if !path.is_ident("validate") {
+                emit_error!(path.span(), "Unknown attribute");
+                continue;
+            }
+
+            let Some(lit) = expr_take_lit_str(value) else { continue };
+            let Ok(expr) = lit.parse::<syn::ExprPath>() else {
+                emit_error!(lit, "Expected valid identifier");
+                continue;
+            };
+
+            if validator.replace(expr).is_some() {
+                emit_error!(lit, "Duplicate validator");
+            }
+        }
+    }
+
+    validator
+}
+
 fn from_meta_list(meta_list: syn::MetaList) -> Option<FieldProperty> {
     let mut r = FieldProperty::default();
     let span = meta_list.span();
@@ -527,6 +625,13 @@ fn from_meta_list(meta_list: syn::MetaList) -> Option<FieldProperty> {
                     };
 
                     r.one_of = Some(one_of);
+                } else if is_("previous_names") {
+                    let Expr::Array(names) = value else {
+                        emit_error!(value, "Expected array literal");
+                        continue;
+                    };
+
+                    r.previous_names = Some(names);
                 } else if is_("validate_with") {
                     r.validate_with = expr_take_lit_str(value);
                 } else if is_("env_once") {
@@ -535,6 +640,8 @@ fn from_meta_list(meta_list: syn::MetaList) -> Option<FieldProperty> {
                     r.env = expr_take_lit_str(value).map(|x| (false, x));
                 } else if is_("editor") {
                     r.editor = Some(value);
+                } else if is_("deprecated") {
+                    r.deprecated = expr_take_lit_str(value);
                 } else {
                     emit_error!(path.span(), "Unknown attribute")
                 }
@@ -545,6 +652,7 @@ fn from_meta_list(meta_list: syn::MetaList) -> Option<FieldProperty> {
     Some(r)
 }
 
+#[allow(clippy::large_enum_variant)]
 enum FieldType {
     Plain,
     PlainWithDefaultExpr(Expr),
@@ -580,6 +688,8 @@ struct FieldProperty {
     one_of: Option<syn::ExprArray>,
     env: Option<(bool, syn::LitStr)>, // (IsOnce, EnvKey)
     validate_with: Option<syn::LitStr>,
+    previous_names: Option<syn::ExprArray>,
+    deprecated: Option<syn::LitStr>,
     transient: bool,
     no_export: bool,
     no_import: bool,
@@ -0,0 +1,168 @@
+//! Item-level change events of a storage.
+//!
+//! Unlike the group update notification, which only tells that something in the group changed,
+//! each [`ChangeEvent`] carries the changed item with its previous and new value. Events are
+//! delivered only to the receivers created with
+//! [`Storage::subscribe_changes`](super::storage::Storage::subscribe_changes), thus values are not
+//! serialized unless there's any.
+//!
+//! A receiver keeps at most [`MAX_PENDING_EVENTS`] events. If it's not drained in time, pending
+//! events are discarded and a single [`ChangeNotice::Overflow`] is delivered in their place.
+
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    task::{Poll, Waker},
+};
+
+use parking_lot::{Mutex, RwLock};
+use strseq::SharedStringSequence;
+
+use super::{noti::TryWaitError, storage::UpdateOrigin};
+use crate::shared::{GroupId, ItemId};
+
+/// A value of an item was replaced.
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    pub group_id: GroupId,
+    pub path: SharedStringSequence,
+    pub item_id: ItemId,
+    pub name: &'static str,
+
+    /// Values of `SECRET` items are always `null`.
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+
+    pub origin: UpdateOrigin,
+
+    /// Version of the item after the change.
+    pub version: u64,
+}
+
+/// Maximum number of pending events of single receiver.
+pub const MAX_PENDING_EVENTS: usize = 4096;
+
+/// Item delivered by [`ChangeReceiver`].
+#[derive(Debug, Clone)]
+pub enum ChangeNotice {
+    Changed(ChangeEvent),
+
+    /// Receiver was not drained in time, and some events were discarded. Consumer should treat
+    /// every item as changed.
+    Overflow,
+}
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<ChangeEvent>,
+    overflowed: bool,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+impl Queue {
+    fn pop(&mut self) -> Option<ChangeNotice> {
+        if std::mem::take(&mut self.overflowed) {
+            return Some(ChangeNotice::Overflow);
+        }
+
+        self.events.pop_front().map(ChangeNotice::Changed)
+    }
+}
+
+/// Receives change events of a storage, in order of application. Dropping the receiver
+/// unsubscribes.
+///
+/// Implements [`futures_core::Stream`], which ends once the storage is dropped.
+pub struct ChangeReceiver(Arc<Mutex<Queue>>);
+
+impl ChangeReceiver {
+    pub fn try_recv(&mut self) -> Result<ChangeNotice, TryWaitError> {
+        let mut queue = self.0.lock();
+        match queue.pop() {
+            Some(notice) => Ok(notice),
+            None if queue.closed => Err(TryWaitError::Closed),
+            None => Err(TryWaitError::Empty),
+        }
+    }
+}
+
+impl futures_core::Stream for ChangeReceiver {
+    type Item = ChangeNotice;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<ChangeNotice>> {
+        let mut queue = self.0.lock();
+        match queue.pop() {
+            Some(notice) => Poll::Ready(Some(notice)),
+            None if queue.closed => Poll::Ready(None),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Subscribers of a storage.
+#[derive(Default)]
+pub(crate) struct ChangeChannel {
+    queues: RwLock<Vec<Arc<Mutex<Queue>>>>,
+}
+
+impl ChangeChannel {
+    pub fn subscribe(&self) -> ChangeReceiver {
+        let queue = Arc::new(Mutex::new(Queue::default()));
+        self.queues.write().push(queue.clone());
+        ChangeReceiver(queue)
+    }
+
+    /// Delivers the event to every receiver. The event is built only if there's any.
+    pub fn emit(&self, event: impl FnOnce() -> ChangeEvent) {
+        let mut disposed = false;
+        {
+            let queues = self.queues.read();
+            if queues.is_empty() {
+                return;
+            }
+
+            let event = event();
+            for queue in queues.iter() {
+                // Only the channel holds the queue, once its receiver was dropped.
+                if Arc::strong_count(queue) == 1 {
+                    disposed = true;
+                    continue;
+                }
+
+                let mut queue = queue.lock();
+                if queue.events.len() >= MAX_PENDING_EVENTS {
+                    queue.events.clear();
+                    queue.overflowed = true;
+                }
+
+                queue.events.push_back(event.clone());
+                if let Some(waker) = queue.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+
+        if disposed {
+            self.queues.write().retain(|x| Arc::strong_count(x) > 1);
+        }
+    }
+}
+
+impl Drop for ChangeChannel {
+    fn drop(&mut self) {
+        for queue in self.queues.get_mut().drain(..) {
+            let mut queue = queue.lock();
+            queue.closed = true;
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Command-line arguments as a configuration source.
+//!
+//! Each importable item is addressed by its group path and name joined with dots, e.g.
+//! `--net.server.port=8080` or `--net.server.port 8080`. Values are read as JSON, and fall back to
+//! plain strings, so `--app.name=hello` works without quotes. `--set net.server.port=8080` takes
+//! strict JSON instead. Boolean items may be given without value to set them `true`.
+//!
+//! ```ignore
+//! let cli = CommandLine::from_storage(&storage);
+//! match cli.parse(std::env::args().skip(1)) {
+//!     Ok(archive) => storage.set_layer(Layer::CommandLine, archive),
+//!     Err(CliError::Help) => print!("{}", cli.help()),
+//!     Err(e) => panic!("{e}"),
+//! }
+//! ```
+
+use std::{collections::HashMap, fmt::Write};
+
+use super::{
+    entity::{Entity, EntityValue, PropertyInfo},
+    group::Template,
+    storage::Storage,
+};
+use crate::shared::{archive::Archive, meta::MetaFlag};
+
+#[derive(thiserror::Error, Debug)]
+pub enum CliError {
+    #[error("Help requested")]
+    Help,
+
+    #[error("Unknown configuration key `{0}`")]
+    UnknownKey(String),
+
+    #[error("Missing value for `{0}`")]
+    MissingValue(String),
+
+    #[error("Unexpected argument `{0}`")]
+    UnexpectedArgument(String),
+
+    #[error("Invalid value for `{key}` of type `{type_name}`: {message}")]
+    InvalidValue { key: String, type_name: &'static str, message: String },
+}
+
+/// Parses command-line arguments into an archive, for items of registered groups.
+#[derive(Default)]
+pub struct CommandLine {
+    groups: Vec<(Vec<String>, Vec<&'static PropertyInfo>)>,
+}
+
+impl CommandLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts items of every live group of the storage.
+    pub fn from_storage(storage: &Storage) -> Self {
+        let mut groups: Vec<_> = storage
+            .group_contexts()
+            .into_iter()
+            .map(|ctx| {
+                let path: Vec<_> = ctx.path.iter().map(ToOwned::to_owned).collect();
+                (path, ctx.sources.iter().map(|x| x.meta).collect())
+            })
+            .collect();
+
+        groups.sort_by(|a, b| a.0.cmp(&b.0));
+        Self { groups }
+    }
+
+    /// Accepts items of template `T` at given path, which doesn't need to be instantiated.
+    pub fn add_template<T: Template>(
+        &mut self,
+        path: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> &mut Self {
+        let path = path.into_iter().map(|x| x.as_ref().to_owned()).collect();
+        self.groups.push((path, T::props__().iter().collect()));
+        self
+    }
+
+    /// Importable items by their keys, including the keys of previous names.
+    fn keys(&self) -> HashMap<String, (&[String], &'static PropertyInfo)> {
+        let mut keys = HashMap::new();
+        for (path, props) in &self.groups {
+            for &prop in props.iter().filter(|x| !x.flags.contains(MetaFlag::NO_IMPORT)) {
+                for name in prop.archive_keys() {
+                    keys.entry(key_of(path, name)).or_insert((&path[..], prop));
+                }
+            }
+        }
+        keys
+    }
+
+    /// Parses arguments, excluding the program name. Arguments after `--` are ignored.
+    ///
+    /// Returns [`CliError::Help`] on `--help` or `-h`.
+    pub fn parse(
+        &self,
+        args: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Archive, CliError> {
+        let keys = self.keys();
+        let mut archive = Archive::default();
+        let mut args = args.into_iter().map(|x| x.as_ref().to_owned()).peekable();
+
+        while let Some(arg) = args.next() {
+            let (key, value, strict) = match arg.as_str() {
+                "--" => break,
+                "--help" | "-h" => return Err(CliError::Help),
+                "--set" => {
+                    let Some(assign) = args.next() else {
+                        return Err(CliError::MissingValue(arg));
+                    };
+                    let Some((key, value)) = assign.split_once('=') else {
+                        return Err(CliError::MissingValue(assign));
+                    };
+                    (key.to_owned(), Some(value.to_owned()), true)
+                }
+                _ => {
+                    let Some(flag) = arg.strip_prefix("--") else {
+                        return Err(CliError::UnexpectedArgument(arg));
+                    };
+
+                    match flag.split_once('=') {
+                        Some((key, value)) => (key.to_owned(), Some(value.to_owned()), false),
+                        None => {
+                            let value = args.next_if(|x| !x.starts_with("--"));
+                            (flag.to_owned(), value, false)
+                        }
+                    }
+                }
+            };
+
+            let Some(&(path, prop)) = keys.get(&key) else {
+                return Err(CliError::UnknownKey(key));
+            };
+
+            let value = match value {
+                Some(value) => parse_value(prop, &value, strict).map(|x| x.0),
+                None if is_bool(prop) => Ok(true.into()),
+                None => return Err(CliError::MissingValue(key)),
+            };
+
+            let value = value.map_err(|message| CliError::InvalidValue {
+                key,
+                type_name: prop.type_name,
+                message,
+            })?;
+
+            let node = archive.find_or_create_path_mut(path.iter().map(String::as_str));
+            node.insert_value(prop.name, value);
+        }
+
+        Ok(archive)
+    }
+
+    /// Describes every accepted flag, with its description and default.
+    pub fn help(&self) -> String {
+        let mut out = String::from("Options:\n");
+
+        for (path, props) in &self.groups {
+            for &prop in props.iter().filter(|x| !x.flags.contains(MetaFlag::NO_IMPORT)) {
+                let _ = writeln!(out, "  --{}=<{}>", key_of(path, prop.name), prop.type_name);
+
+                for line in prop.description.lines().map(str::trim).filter(|x| !x.is_empty()) {
+                    let _ = writeln!(out, "        {line}");
+                }
+
+                if !prop.flags.contains(MetaFlag::SECRET) {
+                    let default = prop.vtable.create_default();
+                    if let Ok(json) = serde_json::to_string(default.as_serialize()) {
+                        let _ = writeln!(out, "        Default: {json}");
+                    }
+                }
+
+                if let Some(note) = prop.deprecated {
+                    let _ = writeln!(out, "        Deprecated: {note}");
+                }
+            }
+        }
+
+        out.push_str("  --set <key>=<json>\n        Sets any option above with a JSON value.\n");
+        out.push_str("  -h, --help\n        Prints this help.\n");
+        out
+    }
+}
+
+fn key_of(path: &[String], name: &str) -> String {
+    path.iter().map(String::as_str).chain([name]).collect::<Vec<_>>().join(".")
+}
+
+fn is_bool(prop: &PropertyInfo) -> bool {
+    prop.vtable.create_default().as_any().is::<bool>()
+}
+
+/// Parses the value as JSON, or as plain string unless `strict`. The value must deserialize into
+/// the item type, which is returned together.
+pub(crate) fn parse_value(
+    prop: &PropertyInfo,
+    value: &str,
+    strict: bool,
+) -> Result<(serde_json::Value, EntityValue), String> {
+    let check = |json: serde_json::Value| {
+        let result = prop.vtable.deserialize(&mut <dyn erased_serde::Deserializer>::erase(&json));
+        result.map(|built| (json, built)).map_err(|e| e.to_string())
+    };
+
+    match (serde_json::from_str(value), strict) {
+        (Ok(json), true) => check(json),
+        (Ok(json), false) => check(json).or_else(|e| check(value.into()).map_err(|_| e)),
+        (Err(e), true) => Err(e.to_string()),
+        (Err(_), false) => check(value.into()),
+    }
+}
@@ -0,0 +1,251 @@
+use serde_json::Value as Json;
+
+use super::CodecError;
+use crate::shared::archive::Archive;
+
+/// Maximum depth of category path which can be represented by section name.
+const MAX_DEPTH: usize = 2;
+
+pub(super) fn decode(text: &str) -> Result<Archive, CodecError> {
+    let mut archive = Archive::default();
+    let mut section = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: &str| CodecError::Ini { line: index + 1, message: message.into() };
+
+        match parse_line(line) {
+            Line::Blank => {}
+            Line::Section(name) => {
+                section = name.split('.').map(str::trim).collect();
+                if section.len() > MAX_DEPTH || section.iter().any(|x| x.is_empty()) {
+                    return Err(error("Invalid section name"));
+                }
+
+                archive.find_or_create_path_mut(section.iter().copied());
+            }
+            Line::Entry(key, value) => {
+                let node = archive.find_or_create_path_mut(section.iter().copied());
+                node.insert_value(key, decode_value(value));
+            }
+            Line::Invalid => return Err(error("Expected section or `key = value`")),
+        }
+    }
+
+    Ok(archive)
+}
+
+pub(super) fn encode(archive: &Archive) -> Result<String, CodecError> {
+    let mut out = String::new();
+    write_values(&mut out, archive)?;
+
+    for (key, node) in archive.iter_paths() {
+        check_section_name(key)?;
+
+        // Header of a section which only holds subsections is implied by them.
+        if !node.is_empty_values() || node.is_empty_paths() {
+            write_header(&mut out, key);
+            write_values(&mut out, node)?;
+        }
+
+        for (sub_key, sub_node) in node.iter_paths() {
+            check_section_name(sub_key)?;
+
+            if !sub_node.is_empty_paths() {
+                return Err(CodecError::Unsupported(format!(
+                    "Category path deeper than {MAX_DEPTH} levels: {key}.{sub_key}"
+                )));
+            }
+
+            write_header(&mut out, &format!("{key}.{sub_key}"));
+            write_values(&mut out, sub_node)?;
+        }
+    }
+
+    Ok(out)
+}
+
+pub(super) enum Line<'a> {
+    Blank,
+    Section(&'a str),
+    Entry(&'a str, &'a str),
+    Invalid,
+}
+
+/// Parses single line. Comments are started with `;` or `#`.
+pub(super) fn parse_line(line: &str) -> Line<'_> {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+        Line::Blank
+    } else if let Some(name) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+        Line::Section(name.trim())
+    } else if let Some((key, value)) = line.split_once('=') {
+        Line::Entry(key.trim(), value.trim())
+    } else {
+        Line::Invalid
+    }
+}
+
+/// Values are JSON literals. Anything else is taken as a plain string.
+fn decode_value(text: &str) -> Json {
+    serde_json::from_str(text).unwrap_or_else(|_| Json::String(text.into()))
+}
+
+pub(super) fn encode_value(value: &Json) -> Result<String, CodecError> {
+    Ok(serde_json::to_string(value)?)
+}
+
+fn write_header(out: &mut String, name: &str) {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+
+    out.push_str(&format!("[{name}]\n"));
+}
+
+fn write_values(out: &mut String, node: &Archive) -> Result<(), CodecError> {
+    for (key, value) in node.iter_values() {
+        check_key(key)?;
+        out.push_str(&format!("{key} = {}\n", encode_value(value)?));
+    }
+
+    Ok(())
+}
+
+pub(super) fn check_key(key: &str) -> Result<(), CodecError> {
+    let invalid = key.is_empty()
+        || key.trim() != key
+        || key.contains(['=', '\n', '\r'])
+        || key.starts_with([';', '#', '[']);
+
+    if invalid {
+        return Err(CodecError::Unsupported(format!("INI key {key:?}")));
+    }
+
+    Ok(())
+}
+
+fn check_section_name(name: &str) -> Result<(), CodecError> {
+    if name.is_empty() || name.trim() != name || name.contains(['.', ']', '\n', '\r']) {
+        return Err(CodecError::Unsupported(format!("INI section name {name:?}")));
+    }
+
+    Ok(())
+}
+
+/* ---------------------------------------- Patching ---------------------------------------- */
+
+/// Applies the patch onto the original document text, editing only the affected lines.
+pub(super) fn patch(
+    original: &str,
+    patch: &Archive,
+    describe: super::DescribeFn,
+) -> Result<String, CodecError> {
+    let mut lines: Vec<String> = original.lines().map(str::to_owned).collect();
+
+    patch_section(&mut lines, &[], patch, describe)?;
+    for (key, node) in patch.iter_paths() {
+        check_section_name(key)?;
+
+        if !node.is_empty_values() || node.is_empty_paths() {
+            patch_section(&mut lines, &[key], node, describe)?;
+        }
+
+        for (sub_key, sub_node) in node.iter_paths() {
+            check_section_name(sub_key)?;
+
+            if !sub_node.is_empty_paths() {
+                return Err(CodecError::Unsupported(format!(
+                    "Category path deeper than {MAX_DEPTH} levels: {key}.{sub_key}"
+                )));
+            }
+
+            patch_section(&mut lines, &[key, sub_key], sub_node, describe)?;
+        }
+    }
+
+    let mut text = lines.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+
+    Ok(text)
+}
+
+fn patch_section(
+    lines: &mut Vec<String>,
+    path: &[&str],
+    node: &Archive,
+    describe: super::DescribeFn,
+) -> Result<(), CodecError> {
+    let name = path.join(".");
+
+    for (key, value) in node.iter_values() {
+        check_key(key)?;
+        let value = encode_value(value)?;
+        let (start, end) =
+            find_section(lines, &name).unwrap_or_else(|| append_section(lines, &name));
+
+        let existing =
+            (start..end).find(|&i| matches!(parse_line(&lines[i]), Line::Entry(k, _) if k == key));
+        if let Some(index) = existing {
+            let line = &lines[index];
+            let eq = line.find('=').unwrap();
+            let space = if line[eq + 1..].starts_with(char::is_whitespace) { " " } else { "" };
+            lines[index] = format!("{}{space}{value}", &line[..=eq]);
+            continue;
+        }
+
+        let last_entry =
+            (start..end).rev().find(|&i| matches!(parse_line(&lines[i]), Line::Entry(..)));
+        let at = match last_entry {
+            Some(index) => index + 1,
+            None if name.is_empty() => 0,
+            None => start,
+        };
+
+        let mut new_lines: Vec<_> = describe(path, key)
+            .iter()
+            .flat_map(|x| x.lines())
+            .map(|x| format!("; {x}").trim_end().to_owned())
+            .collect();
+        new_lines.push(format!("{key} = {value}"));
+        lines.splice(at..at, new_lines);
+    }
+
+    if !name.is_empty() && find_section(lines, &name).is_none() {
+        append_section(lines, &name);
+    }
+
+    Ok(())
+}
+
+/// Finds the range of entry lines of given section, excluding the header. Empty name designates
+/// the root section.
+fn find_section(lines: &[String], name: &str) -> Option<(usize, usize)> {
+    let mut start = name.is_empty().then_some(0);
+
+    for (index, line) in lines.iter().enumerate() {
+        if let Line::Section(section) = parse_line(line) {
+            if let Some(start) = start {
+                return Some((start, index));
+            }
+
+            let section: Vec<_> = section.split('.').map(str::trim).collect();
+            if section.join(".") == name {
+                start = Some(index + 1);
+            }
+        }
+    }
+
+    start.map(|x| (x, lines.len()))
+}
+
+fn append_section(lines: &mut Vec<String>, name: &str) -> (usize, usize) {
+    if lines.last().is_some_and(|x| !x.trim().is_empty()) {
+        lines.push(String::new());
+    }
+
+    lines.push(format!("[{name}]"));
+    (lines.len(), lines.len())
+}
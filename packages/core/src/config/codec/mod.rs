@@ -0,0 +1,160 @@
+//! Text representations of [`Archive`], which are used by file based sources.
+//!
+//! Besides plain JSON, feature gated codecs map [`Archive`] categories onto the native nesting
+//! constructs of each format, therefore no `~` prefixed keys appear in the file:
+//!
+//! - `toml`: Categories are written as tables, values as keys. Object values are written as inline
+//!   tables, so that they're not confused with categories.
+//! - `yaml`: Categories are written as nested maps, values as keys. Object values are tagged with
+//!   `!value`, so that they're not confused with categories.
+//! - `ini`: Sections are categories, with `.` delimiting up to two levels of path. Keys before the
+//!   first section are root values. Values are written as JSON literals; unquoted text is read as
+//!   a plain string.
+
+use std::path::Path;
+
+use crate::shared::archive::Archive;
+
+#[cfg(feature = "ini")]
+mod ini;
+#[cfg(feature = "toml")]
+mod toml;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+/// Describes an item with its category path and name. Descriptions are written as comments above
+/// the keys newly inserted by [`Format::patch_document`].
+pub type DescribeFn<'a> = &'a dyn Fn(&[&str], &str) -> Option<String>;
+
+/// Supported archive file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Format {
+    /// Plain JSON representation of [`Archive`]. Written as pretty-printed.
+    Json,
+
+    /// TOML document. Can't represent `null`; null values are omitted on write.
+    #[cfg(feature = "toml")]
+    Toml,
+
+    /// YAML document.
+    #[cfg(feature = "yaml")]
+    Yaml,
+
+    /// INI document, which supports category paths up to two levels.
+    #[cfg(feature = "ini")]
+    Ini,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CodecError {
+    #[error("JSON codec error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[cfg(feature = "toml")]
+    #[error("TOML codec error: {0}")]
+    Toml(#[from] toml_edit::TomlError),
+
+    #[cfg(feature = "yaml")]
+    #[error("YAML codec error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[cfg(feature = "ini")]
+    #[error("INI syntax error at line {line}: {message}")]
+    Ini { line: usize, message: String },
+
+    #[error("Value can't be represented in this format: {0}")]
+    Unsupported(String),
+}
+
+impl Format {
+    /// Detects format from file extension of given path. Extension is matched case-insensitively.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        Self::from_extension(path.as_ref().extension()?.to_str()?)
+    }
+
+    /// Detects format from file extension, without leading dot.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            #[cfg(feature = "toml")]
+            "toml" => Some(Self::Toml),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(Self::Yaml),
+            #[cfg(feature = "ini")]
+            "ini" | "cfg" | "conf" => Some(Self::Ini),
+            _ => None,
+        }
+    }
+
+    /// Parses text into archive.
+    pub fn decode(self, text: &str) -> Result<Archive, CodecError> {
+        match self {
+            Self::Json => Ok(serde_json::from_str(text)?),
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::decode(text),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => yaml::decode(text),
+            #[cfg(feature = "ini")]
+            Self::Ini => ini::decode(text),
+        }
+    }
+
+    /// Serializes archive into text.
+    pub fn encode(self, archive: &Archive) -> Result<String, CodecError> {
+        match self {
+            Self::Json => Ok(serde_json::to_string_pretty(archive)?),
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::encode(archive),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => yaml::encode(archive),
+            #[cfg(feature = "ini")]
+            Self::Ini => ini::encode(archive),
+        }
+    }
+
+    /// Applies a patch created by [`Archive::create_patch`] onto the original document text,
+    /// updating only the changed keys in place. Comments and key ordering of the original are
+    /// kept, and new keys are added next to their siblings, preceded by their descriptions.
+    ///
+    /// If the original document can't be edited in place, it's rewritten as a whole. JSON has no
+    /// comments, thus it's always rewritten.
+    #[cfg_attr(
+        not(any(feature = "toml", feature = "yaml", feature = "ini")),
+        allow(unused_variables)
+    )]
+    pub fn patch_document(
+        self,
+        original: &str,
+        patch: &Archive,
+        describe: DescribeFn,
+    ) -> Result<String, CodecError> {
+        if patch.is_empty() {
+            return Ok(original.to_owned());
+        }
+
+        let expected = self.decode(original)?.merge(patch.clone());
+
+        let patched: Option<String> = match self {
+            Self::Json => None,
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::patch(original, patch, describe).ok(),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => yaml::patch(original, patch, describe),
+            #[cfg(feature = "ini")]
+            Self::Ini => Some(ini::patch(original, patch, describe)?),
+        };
+
+        // Edited documents are verified, since the in-place editors don't understand every
+        // construct of each format.
+        let rewritten = self.encode(&expected)?;
+        match patched {
+            Some(text) if self.decode(&text).ok() == Some(self.decode(&rewritten)?) => Ok(text),
+            Some(_) => {
+                tr::debug!(format = ?self, "In-place edit mismatch. Rewriting whole document.");
+                Ok(rewritten)
+            }
+            None => Ok(rewritten),
+        }
+    }
+}
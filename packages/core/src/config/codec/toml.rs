@@ -0,0 +1,201 @@
+use serde_json::Value as Json;
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, Value};
+
+use super::CodecError;
+use crate::shared::archive::Archive;
+
+pub(super) fn decode(text: &str) -> Result<Archive, CodecError> {
+    let doc: DocumentMut = text.parse()?;
+    Ok(decode_table(doc.as_table()))
+}
+
+pub(super) fn encode(archive: &Archive) -> Result<String, CodecError> {
+    let mut doc = DocumentMut::new();
+    *doc.as_table_mut() = encode_table(archive)?;
+    Ok(doc.to_string())
+}
+
+/* ------------------------------------------ Decoding ------------------------------------------ */
+
+fn decode_table(table: &Table) -> Archive {
+    let mut archive = Archive::default();
+
+    for (key, item) in table.iter() {
+        match item {
+            Item::Table(table) => archive.insert_path(key, decode_table(table)),
+            Item::Value(value) => archive.insert_value(key, decode_value(value)),
+            Item::ArrayOfTables(array) => archive.insert_value(
+                key,
+                Json::Array(array.iter().map(|x| Json::Object(table_to_object(x))).collect()),
+            ),
+            Item::None => {}
+        }
+    }
+
+    archive
+}
+
+/// Converts a table which is part of a value, rather than a category.
+fn table_to_object(table: &Table) -> serde_json::Map<String, Json> {
+    table
+        .iter()
+        .filter_map(|(key, item)| {
+            let value = match item {
+                Item::Table(table) => Json::Object(table_to_object(table)),
+                Item::Value(value) => decode_value(value),
+                Item::ArrayOfTables(array) => {
+                    Json::Array(array.iter().map(|x| Json::Object(table_to_object(x))).collect())
+                }
+                Item::None => return None,
+            };
+
+            Some((key.to_owned(), value))
+        })
+        .collect()
+}
+
+pub(super) fn decode_value(value: &Value) -> Json {
+    match value {
+        Value::String(x) => Json::String(x.value().clone()),
+        Value::Integer(x) => Json::from(*x.value()),
+        Value::Float(x) => Json::from(*x.value()),
+        Value::Boolean(x) => Json::Bool(*x.value()),
+        Value::Datetime(x) => Json::String(x.value().to_string()),
+        Value::Array(x) => Json::Array(x.iter().map(decode_value).collect()),
+        Value::InlineTable(x) => {
+            Json::Object(x.iter().map(|(k, v)| (k.to_owned(), decode_value(v))).collect())
+        }
+    }
+}
+
+/* ------------------------------------------ Patching ------------------------------------------ */
+
+/// Applies the patch onto the original document, preserving its comments and formatting.
+pub(super) fn patch(
+    original: &str,
+    patch: &Archive,
+    describe: super::DescribeFn,
+) -> Result<String, CodecError> {
+    let mut doc: DocumentMut = original.parse()?;
+    patch_table(doc.as_table_mut(), patch, &mut Vec::new(), describe)?;
+    Ok(doc.to_string())
+}
+
+fn patch_table<'a>(
+    table: &mut Table,
+    patch: &'a Archive,
+    path: &mut Vec<&'a str>,
+    describe: super::DescribeFn,
+) -> Result<(), CodecError> {
+    for (key, value) in patch.iter_values() {
+        let Some(mut value) = encode_value(value)? else {
+            table.remove(key);
+            continue;
+        };
+
+        match table.get_mut(key) {
+            Some(Item::Value(old)) => {
+                *value.decor_mut() = old.decor().clone();
+                *old = value;
+            }
+            Some(item) => *item = Item::Value(value),
+            None => {
+                table.insert(key, Item::Value(value));
+
+                if let Some(description) = describe(path, key) {
+                    let comment: String = description
+                        .lines()
+                        .map(|x| format!("# {x}").trim_end().to_owned() + "\n")
+                        .collect();
+                    table.key_mut(key).unwrap().leaf_decor_mut().set_prefix(comment);
+                }
+            }
+        }
+    }
+
+    for (key, child) in patch.iter_paths() {
+        let item = table.entry(key).or_insert_with(|| {
+            // Header is emitted only if the table has any value.
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+        if !item.is_table() {
+            *item = Item::Table(Table::new());
+        }
+
+        let child_table = item.as_table_mut().unwrap();
+        path.push(key);
+        patch_table(child_table, child, path, describe)?;
+        path.pop();
+
+        if child_table.is_empty() {
+            child_table.set_implicit(false);
+        }
+    }
+
+    Ok(())
+}
+
+/* ------------------------------------------ Encoding ------------------------------------------ */
+
+fn encode_table(archive: &Archive) -> Result<Table, CodecError> {
+    let mut table = Table::new();
+
+    for (key, value) in archive.iter_values() {
+        if let Some(value) = encode_value(value)? {
+            table.insert(key, Item::Value(value));
+        }
+    }
+
+    for (key, child) in archive.iter_paths() {
+        let mut child_table = encode_table(child)?;
+
+        // Don't emit empty headers for tables which only hold subtables. Empty categories are
+        // still written, to be read back.
+        child_table.set_implicit(child.is_empty_values() && !child.is_empty_paths());
+        table.insert(key, Item::Table(child_table));
+    }
+
+    Ok(table)
+}
+
+/// Returns `None` for null, which TOML can't represent.
+pub(super) fn encode_value(value: &Json) -> Result<Option<Value>, CodecError> {
+    Ok(Some(match value {
+        Json::Null => return Ok(None),
+        Json::Bool(x) => (*x).into(),
+        Json::String(x) => x.as_str().into(),
+        Json::Number(x) => {
+            if let Some(x) = x.as_i64() {
+                x.into()
+            } else if let Some(x) = x.as_f64().filter(|_| x.is_f64()) {
+                x.into()
+            } else {
+                return Err(CodecError::Unsupported(format!("Integer {x} exceeds i64")));
+            }
+        }
+        Json::Array(items) => {
+            let mut array = Array::new();
+            for item in items {
+                let Some(item) = encode_value(item)? else {
+                    return Err(CodecError::Unsupported("null in array".into()));
+                };
+
+                array.push(item);
+            }
+
+            array.into()
+        }
+        Json::Object(map) => {
+            let mut table = InlineTable::new();
+            for (key, item) in map {
+                if let Some(item) = encode_value(item)? {
+                    table.insert(key, item);
+                }
+            }
+
+            table.into()
+        }
+    }))
+}
@@ -0,0 +1,365 @@
+use serde_json::Value as Json;
+use serde_yaml::{value::TaggedValue, Mapping, Value};
+
+use super::CodecError;
+use crate::shared::archive::Archive;
+
+/// Tag of object values, which distinguishes them from categories.
+pub(super) const VALUE_TAG: &str = "!value";
+
+pub(super) fn decode(text: &str) -> Result<Archive, CodecError> {
+    match serde_yaml::from_str::<Value>(text)? {
+        Value::Null => Ok(Archive::default()),
+        Value::Mapping(map) => decode_mapping(map),
+        _ => Err(CodecError::Unsupported("YAML root must be a map".into())),
+    }
+}
+
+pub(super) fn encode(archive: &Archive) -> Result<String, CodecError> {
+    Ok(serde_yaml::to_string(&encode_mapping(archive)?)?)
+}
+
+fn decode_mapping(map: Mapping) -> Result<Archive, CodecError> {
+    let mut archive = Archive::default();
+
+    for (key, value) in map {
+        let key = key_string(key)?;
+
+        match value {
+            Value::Mapping(map) => archive.insert_path(key, decode_mapping(map)?),
+            Value::Tagged(tagged) if tagged.tag == VALUE_TAG => {
+                archive.insert_value(key, serde_json::to_value(tagged.value)?)
+            }
+            value => archive.insert_value(key, serde_json::to_value(value)?),
+        }
+    }
+
+    Ok(archive)
+}
+
+fn key_string(key: Value) -> Result<String, CodecError> {
+    match key {
+        Value::String(x) => Ok(x),
+        Value::Bool(x) => Ok(x.to_string()),
+        Value::Number(x) => Ok(x.to_string()),
+        key => Err(CodecError::Unsupported(format!("Non-scalar key {key:?}"))),
+    }
+}
+
+fn encode_mapping(archive: &Archive) -> Result<Value, CodecError> {
+    let mut map = Mapping::new();
+
+    for (key, value) in archive.iter_values() {
+        map.insert(key.into(), encode_value(value)?);
+    }
+
+    for (key, child) in archive.iter_paths() {
+        map.insert(key.into(), encode_mapping(child)?);
+    }
+
+    Ok(Value::Mapping(map))
+}
+
+pub(super) fn encode_value(value: &Json) -> Result<Value, CodecError> {
+    let yaml = serde_yaml::to_value(value)?;
+
+    Ok(if value.is_object() {
+        Value::Tagged(Box::new(TaggedValue {
+            tag: serde_yaml::value::Tag::new(VALUE_TAG),
+            value: yaml,
+        }))
+    } else {
+        yaml
+    })
+}
+
+/* ---------------------------------------- Patching ---------------------------------------- */
+
+/// Applies the patch onto the original document text, editing block mappings line by line.
+/// Returns `None` if the document uses constructs which can't be edited in place.
+pub(super) fn patch(
+    original: &str,
+    patch: &Archive,
+    describe: super::DescribeFn,
+) -> Option<String> {
+    let mut doc = Document {
+        lines: original.lines().map(str::to_owned).collect(),
+        trailing_newline: original.is_empty() || original.ends_with('\n'),
+    };
+
+    // Empty archive is written as a flow map.
+    if doc.content_lines().eq(["{}"]) {
+        doc.lines.retain(|x| x.trim() != "{}");
+    }
+
+    doc.apply(patch, &mut Vec::new(), describe)?;
+
+    let mut text = doc.lines.join("\n");
+    if doc.trailing_newline && !text.is_empty() {
+        text.push('\n');
+    }
+
+    Some(text)
+}
+
+struct Document {
+    lines: Vec<String>,
+    trailing_newline: bool,
+}
+
+/// Key of block mapping, spanning lines `line..end`.
+struct Entry {
+    key: String,
+    line: usize,
+    end: usize,
+    indent: usize,
+
+    /// Byte offset of the value text within the key line.
+    value_offset: usize,
+}
+
+/// Line range of a block mapping and indentation of its keys.
+#[derive(Clone, Copy)]
+struct Block {
+    start: usize,
+    end: usize,
+    indent: usize,
+}
+
+impl Document {
+    fn content_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(|x| x.trim()).filter(|x| !is_blank(x))
+    }
+
+    fn apply(
+        &mut self,
+        patch: &Archive,
+        path: &mut Vec<String>,
+        describe: super::DescribeFn,
+    ) -> Option<()> {
+        for (key, value) in patch.iter_values() {
+            let block = self.locate(path)?;
+            let rendered = render_value(value).ok()?;
+
+            if let Some(entry) = self.entries(block)?.into_iter().find(|x| x.key == key) {
+                let line = &self.lines[entry.line];
+                let comment = trailing_comment(&line[entry.value_offset..]).unwrap_or("");
+                let head = line[..entry.value_offset].trim_end().to_owned();
+
+                self.lines[entry.line] = format!("{head} {rendered}{comment}");
+                self.lines.drain(entry.line + 1..entry.end);
+            } else {
+                let at = self.insert_point(block)?;
+                let path_refs: Vec<_> = path.iter().map(String::as_str).collect();
+                let mut new_lines = comment_lines(block.indent, describe(&path_refs, key));
+                new_lines.push(format!(
+                    "{:w$}{}: {rendered}",
+                    "",
+                    render_key(key),
+                    w = block.indent
+                ));
+
+                self.lines.splice(at..at, new_lines);
+            }
+        }
+
+        for (key, child) in patch.iter_paths() {
+            path.push(key.to_owned());
+            self.apply(child, path, describe)?;
+            path.pop();
+        }
+
+        // Category without any content must be written as an empty map, not as null.
+        let block = self.locate(path)?;
+        if !path.is_empty() && self.content_indent(block.start, block.end).is_none() {
+            self.lines[block.start - 1].push_str(" {}");
+        }
+
+        Some(())
+    }
+
+    /// Finds the block mapping of given category path, creating missing categories.
+    fn locate(&mut self, path: &[String]) -> Option<Block> {
+        let indent = self.content_indent(0, self.lines.len()).unwrap_or(0);
+        let mut block = Block { start: 0, end: self.lines.len(), indent };
+
+        for key in path {
+            let entries = self.entries(block)?;
+
+            block = if let Some(entry) = entries.into_iter().find(|x| &x.key == key) {
+                let line = &self.lines[entry.line];
+                let value = line[entry.value_offset..].trim();
+                let value = value.strip_suffix(trailing_comment(value).unwrap_or("")).unwrap();
+
+                match value.trim() {
+                    "" => {}
+                    "{}" => {
+                        let head = line[..entry.value_offset].to_owned();
+                        self.lines[entry.line] = head;
+                    }
+                    _ => return None,
+                }
+
+                let (start, end) = (entry.line + 1, entry.end);
+                let indent = self.content_indent(start, end).unwrap_or(entry.indent + 2);
+                Block { start, end, indent }
+            } else {
+                let at = self.insert_point(block)?;
+                let line = format!("{:w$}{}:", "", render_key(key), w = block.indent);
+                self.lines.insert(at, line);
+                Block { start: at + 1, end: at + 1, indent: block.indent + 2 }
+            };
+        }
+
+        Some(block)
+    }
+
+    /// Lines of new keys are inserted right after the last entry of the block.
+    fn insert_point(&self, block: Block) -> Option<usize> {
+        Some(self.entries(block)?.last().map(|x| x.end).unwrap_or(block.start))
+    }
+
+    fn content_indent(&self, start: usize, end: usize) -> Option<usize> {
+        self.lines[start..end].iter().find(|x| !is_blank(x.trim())).map(|x| indent_of(x))
+    }
+
+    fn entries(&self, block: Block) -> Option<Vec<Entry>> {
+        let mut entries = Vec::new();
+        let mut index = block.start;
+
+        while index < block.end {
+            let line = &self.lines[index];
+            if is_blank(line.trim()) {
+                index += 1;
+                continue;
+            }
+
+            if indent_of(line) != block.indent {
+                return None;
+            }
+
+            let (key, value_offset) = parse_key(line, block.indent)?;
+            let mut end = index + 1;
+            let mut scan = index + 1;
+
+            while scan < block.end {
+                let next = &self.lines[scan];
+                let trimmed = next.trim();
+
+                if !is_blank(trimmed) {
+                    let is_child = indent_of(next) > block.indent
+                        || (indent_of(next) == block.indent
+                            && (trimmed == "-" || trimmed.starts_with("- ")));
+
+                    if !is_child {
+                        break;
+                    }
+
+                    end = scan + 1;
+                }
+
+                scan += 1;
+            }
+
+            entries.push(Entry { key, line: index, end, indent: block.indent, value_offset });
+            index = end;
+        }
+
+        Some(entries)
+    }
+}
+
+fn is_blank(trimmed: &str) -> bool {
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Parses `key:` at the start of the line. Returns the key and byte offset right after the colon.
+fn parse_key(line: &str, indent: usize) -> Option<(String, usize)> {
+    let body = &line[indent..];
+
+    let (key, after) = if body.starts_with('"') {
+        let mut iter = serde_json::Deserializer::from_str(body).into_iter::<String>();
+        let key = iter.next()?.ok()?;
+        (key, iter.byte_offset())
+    } else if let Some(quoted) = body.strip_prefix('\'') {
+        let mut key = String::new();
+        let mut chars = quoted.char_indices().peekable();
+
+        let end = loop {
+            let (i, c) = chars.next()?;
+            if c == '\'' {
+                if chars.peek().map(|x| x.1) == Some('\'') {
+                    chars.next();
+                } else {
+                    break i + 2;
+                }
+            }
+            key.push(c);
+        };
+
+        (key, end)
+    } else {
+        if body.starts_with(['-', '?', '{', '[', '!', '&', '*', '|', '>']) {
+            return None;
+        }
+
+        let end = body
+            .match_indices(':')
+            .map(|x| x.0)
+            .find(|&i| body[i + 1..].is_empty() || body[i + 1..].starts_with(' '))?;
+        (body[..end].trim_end().to_owned(), end)
+    };
+
+    body[after..].starts_with(':').then(|| (key, indent + after + 1))
+}
+
+/// Finds the comment at the end of the value text, including preceding whitespace.
+fn trailing_comment(value: &str) -> Option<&str> {
+    let (mut single, mut double, mut prev_space) = (false, false, true);
+
+    for (i, c) in value.char_indices() {
+        match c {
+            '\'' if !double => single = !single,
+            '"' if !single => double = !double,
+            '#' if !single && !double && prev_space => {
+                let start = value[..i].trim_end().len();
+                return Some(&value[start..]);
+            }
+            _ => {}
+        }
+
+        prev_space = c.is_whitespace();
+    }
+
+    None
+}
+
+fn render_key(key: &str) -> String {
+    let plain = !key.is_empty()
+        && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '$'))
+        && !key.starts_with(['-', '.']);
+
+    if plain {
+        key.to_owned()
+    } else {
+        serde_json::to_string(key).unwrap()
+    }
+}
+
+/// Renders the value in flow style. JSON is valid YAML flow content.
+fn render_value(value: &Json) -> Result<String, CodecError> {
+    let json = serde_json::to_string(value)?;
+    Ok(if value.is_object() { format!("{VALUE_TAG} {json}") } else { json })
+}
+
+pub(super) fn comment_lines(indent: usize, description: Option<String>) -> Vec<String> {
+    description
+        .iter()
+        .flat_map(|x| x.lines())
+        .map(|x| format!("{:indent$}# {x}", "").trim_end().to_owned())
+        .collect()
+}
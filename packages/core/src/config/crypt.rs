@@ -0,0 +1,208 @@
+//! Encryption key providers for `SECRET` properties.
+//!
+//! A [`KeyProvider`] is installed into the storage with
+//! [`Storage::set_crypt_key_provider`](super::storage::Storage::set_crypt_key_provider), and is
+//! queried lazily whenever a secret value is encrypted or decrypted. Besides the built-in
+//! providers, any `Fn() -> Result<[u8; 32], CryptKeyError>` closure can serve as a provider, which
+//! covers integration with external key management services.
+//!
+//! Providers which derive the key from a salt (e.g. [`PassphraseKey`]) get a random salt generated
+//! by the storage. The salt is written into every exported archive, under the root value
+//! [`CRYPT_SALT_KEY`], and read back on import, thus the encrypted archive can be moved to other
+//! machines as long as the same passphrase is supplied.
+
+use std::path::PathBuf;
+
+use base64::prelude::*;
+use parking_lot::Mutex;
+
+use super::layer::Layer;
+
+/// Root value key of the archive, which stores base64 encoded salt of the key derivation.
+pub const CRYPT_SALT_KEY: &str = "$crypt-salt";
+
+#[derive(thiserror::Error, Debug)]
+pub enum CryptKeyError {
+    #[error("Environment variable {0:?} is not set")]
+    EnvNotSet(String),
+
+    #[error("I/O error on {path:?}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    #[error("Key derivation requires salt")]
+    MissingSalt,
+
+    #[error(transparent)]
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Provides the AES-256 key to encrypt and decrypt `SECRET` properties.
+pub trait KeyProvider: Send + Sync + 'static {
+    /// Loads the key. `salt` is given only if [`KeyProvider::requires_salt`] returns true.
+    fn load_key(&self, salt: Option<&[u8]>) -> Result<[u8; 32], CryptKeyError>;
+
+    /// Whether the key is derived from the salt stored in the archive.
+    fn requires_salt(&self) -> bool {
+        false
+    }
+}
+
+impl<F> KeyProvider for F
+where
+    F: Fn() -> Result<[u8; 32], CryptKeyError> + Send + Sync + 'static,
+{
+    fn load_key(&self, _: Option<&[u8]>) -> Result<[u8; 32], CryptKeyError> {
+        self()
+    }
+}
+
+/// Fixed key.
+#[derive(Clone)]
+pub struct StaticKey(pub [u8; 32]);
+
+impl StaticKey {
+    /// Creates a key by hashing arbitrary bytes with SHA-256.
+    pub fn from_hashed(bytes: impl AsRef<[u8]>) -> Self {
+        use sha2::{Digest, Sha256};
+        Self(Sha256::digest(bytes).into())
+    }
+}
+
+impl KeyProvider for StaticKey {
+    fn load_key(&self, _: Option<&[u8]>) -> Result<[u8; 32], CryptKeyError> {
+        Ok(self.0)
+    }
+}
+
+/// Derives the key from a passphrase with PBKDF2-HMAC-SHA256, salted with the salt stored in the
+/// archive.
+pub struct PassphraseKey {
+    passphrase: String,
+    rounds: u32,
+
+    /// Last derived key, along with its salt. Derivation is deliberately slow.
+    cached: Mutex<Option<(Vec<u8>, [u8; 32])>>,
+}
+
+impl PassphraseKey {
+    /// Default number of PBKDF2 iterations.
+    pub const DEFAULT_ROUNDS: u32 = 600_000;
+
+    pub fn new(passphrase: impl Into<String>) -> Self {
+        Self {
+            passphrase: passphrase.into(),
+            rounds: Self::DEFAULT_ROUNDS,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Overrides the number of PBKDF2 iterations. Changing it makes existing data undecryptable.
+    pub fn rounds(mut self, rounds: u32) -> Self {
+        self.rounds = rounds;
+        self
+    }
+}
+
+impl KeyProvider for PassphraseKey {
+    fn load_key(&self, salt: Option<&[u8]>) -> Result<[u8; 32], CryptKeyError> {
+        let salt = salt.ok_or(CryptKeyError::MissingSalt)?;
+        let mut cached = self.cached.lock();
+
+        if let Some((_, key)) = cached.as_ref().filter(|(s, _)| s == salt) {
+            return Ok(*key);
+        }
+
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<sha2::Sha256>(
+            self.passphrase.as_bytes(),
+            salt,
+            self.rounds,
+            &mut key,
+        );
+        *cached = Some((salt.to_vec(), key));
+        Ok(key)
+    }
+
+    fn requires_salt(&self) -> bool {
+        true
+    }
+}
+
+/// Reads the key from a file, which contains either raw 32 bytes or base64 encoded key.
+///
+/// The file is read on every access, thus replacing the file takes effect immediately.
+#[derive(Clone)]
+pub struct FileKey(pub PathBuf);
+
+impl KeyProvider for FileKey {
+    fn load_key(&self, _: Option<&[u8]>) -> Result<[u8; 32], CryptKeyError> {
+        let content = std::fs::read(&self.0)
+            .map_err(|source| CryptKeyError::Io { path: self.0.clone(), source })?;
+
+        if let Ok(key) = content.as_slice().try_into() {
+            return Ok(key);
+        }
+
+        let text = std::str::from_utf8(&content).map_err(|_| {
+            CryptKeyError::InvalidKey("Key file is neither 32 bytes nor text".into())
+        })?;
+        decode_base64_key(text)
+    }
+}
+
+/// Reads base64 encoded key from an environment variable.
+#[derive(Clone)]
+pub struct EnvKey(pub String);
+
+impl KeyProvider for EnvKey {
+    fn load_key(&self, _: Option<&[u8]>) -> Result<[u8; 32], CryptKeyError> {
+        let text = std::env::var(&self.0).map_err(|_| CryptKeyError::EnvNotSet(self.0.clone()))?;
+        decode_base64_key(&text)
+    }
+}
+
+/// Result of [`Storage::rotate_crypt_key`](super::storage::Storage::rotate_crypt_key).
+#[derive(Debug, Default)]
+pub struct KeyRotationReport {
+    /// Number of cached encrypted values which were re-encrypted with the new key.
+    pub rotated: usize,
+
+    /// Encrypted values which couldn't be decrypted with the old key. They're left untouched.
+    pub failed: Vec<RotationFailure>,
+}
+
+/// Encrypted value which failed to be rotated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationFailure {
+    /// Layer which holds the value. `None` for the storage's archive cache.
+    pub layer: Option<Layer>,
+
+    /// Path of the group.
+    pub path: Vec<String>,
+
+    /// Name of the item.
+    pub name: String,
+}
+
+/// Decodes base64 encoded 32 byte key. Padding is optional.
+pub fn decode_base64_key(text: &str) -> Result<[u8; 32], CryptKeyError> {
+    let bin = BASE64_STANDARD_NO_PAD
+        .decode(text.trim().trim_end_matches('='))
+        .map_err(|e| CryptKeyError::InvalidKey(e.to_string()))?;
+
+    bin.as_slice()
+        .try_into()
+        .map_err(|_| CryptKeyError::InvalidKey(format!("Expected 32 bytes, got {}", bin.len())))
+}
+
+/// Generates a new random salt.
+pub(crate) fn generate_salt() -> Vec<u8> {
+    use aes_gcm::aead::{rand_core::RngCore, OsRng};
+
+    let mut salt = vec![0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
@@ -0,0 +1,238 @@
+//! Reference documentation of config templates, rendered as Markdown or HTML.
+//!
+//! Each item is documented with its doc comment, type, template default, environment variable,
+//! flags and deprecation notice, grouped by the path of its group.
+//!
+//! ```ignore
+//! let mut doc = ReferenceDoc::from_storage(&storage);
+//! doc.add_template::<Server>(["server"]);
+//! std::fs::write("config.md", doc.to_markdown())?;
+//! ```
+
+use std::fmt::Write;
+
+use super::{
+    entity::{Entity, PropertyInfo},
+    group::Template,
+    storage::Storage,
+};
+use crate::shared::meta::MetaFlag;
+
+/// Collection of documented groups.
+#[derive(Default)]
+pub struct ReferenceDoc {
+    groups: Vec<GroupDoc>,
+}
+
+struct GroupDoc {
+    path: Vec<String>,
+    template_name: (&'static str, &'static str),
+    props: Vec<&'static PropertyInfo>,
+}
+
+impl ReferenceDoc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Documents every live group of the storage, sorted by path.
+    pub fn from_storage(storage: &Storage) -> Self {
+        let mut groups: Vec<_> = storage
+            .group_contexts()
+            .into_iter()
+            .map(|ctx| GroupDoc {
+                path: ctx.path.iter().map(ToOwned::to_owned).collect(),
+                template_name: ctx.template_name,
+                props: ctx.sources.iter().map(|x| x.meta).collect(),
+            })
+            .collect();
+
+        groups.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { groups }
+    }
+
+    /// Documents template `T` at given path, which doesn't need to be instantiated.
+    pub fn add_template<T: Template>(
+        &mut self,
+        path: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> &mut Self {
+        self.groups.push(GroupDoc {
+            path: path.into_iter().map(|x| x.as_ref().to_owned()).collect(),
+            template_name: T::template_name(),
+            props: T::props__().iter().collect(),
+        });
+        self
+    }
+
+    /// Renders the document in Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Configuration Reference\n");
+
+        for group in &self.groups {
+            let (module, name) = group.template_name;
+            let _ =
+                write!(out, "\n## `{}`\n\nTemplate: `{module}::{name}`\n", group.path.join("."));
+
+            for &prop in &group.props {
+                let _ = writeln!(out, "\n### `{}`\n", prop.name);
+
+                for paragraph in paragraphs(prop.description) {
+                    let _ = writeln!(out, "{paragraph}\n");
+                }
+
+                for (label, value) in details(prop) {
+                    let _ = writeln!(out, "- {label}: {}", value.markdown());
+                }
+
+                #[cfg(feature = "jsonschema")]
+                if let Some(schema) = schema_json(prop) {
+                    let _ = write!(
+                        out,
+                        "\n<details><summary>Schema</summary>\n\n```json\n{schema}\n```\n\n</details>\n"
+                    );
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Renders the document as a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let mut out = String::from(concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>Configuration Reference</title>\n</head>\n<body>\n",
+            "<h1>Configuration Reference</h1>\n",
+        ));
+
+        for group in &self.groups {
+            let (module, name) = group.template_name;
+            let _ = write!(
+                out,
+                "<section>\n<h2><code>{}</code></h2>\n<p>Template: <code>{}</code></p>\n",
+                escape(&group.path.join(".")),
+                escape(&format!("{module}::{name}")),
+            );
+
+            for &prop in &group.props {
+                let _ = writeln!(out, "<h3><code>{}</code></h3>", escape(prop.name));
+
+                for paragraph in paragraphs(prop.description) {
+                    let _ = writeln!(out, "<p>{}</p>", escape(&paragraph));
+                }
+
+                out.push_str("<ul>\n");
+                for (label, value) in details(prop) {
+                    let _ = writeln!(out, "<li>{label}: {}</li>", value.html());
+                }
+                out.push_str("</ul>\n");
+
+                #[cfg(feature = "jsonschema")]
+                if let Some(schema) = schema_json(prop) {
+                    let _ = writeln!(
+                        out,
+                        "<details><summary>Schema</summary><pre><code>{}</code></pre></details>",
+                        escape(&schema)
+                    );
+                }
+            }
+
+            out.push_str("</section>\n");
+        }
+
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+enum Detail {
+    Code(Vec<String>),
+    Text(String),
+}
+
+impl Detail {
+    fn code(text: impl Into<String>) -> Self {
+        Self::Code(vec![text.into()])
+    }
+
+    fn markdown(&self) -> String {
+        match self {
+            Self::Code(x) => x.iter().map(|x| format!("`{x}`")).collect::<Vec<_>>().join(", "),
+            Self::Text(x) => x.clone(),
+        }
+    }
+
+    fn html(&self) -> String {
+        match self {
+            Self::Code(x) => x
+                .iter()
+                .map(|x| format!("<code>{}</code>", escape(x)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Self::Text(x) => escape(x),
+        }
+    }
+}
+
+fn details(prop: &PropertyInfo) -> Vec<(&'static str, Detail)> {
+    let mut details = vec![("Type", Detail::code(prop.type_name))];
+
+    if prop.flags.contains(MetaFlag::SECRET) {
+        details.push(("Default", Detail::Text("(secret)".into())));
+    } else {
+        let default = prop.vtable.create_default();
+        if let Ok(json) = serde_json::to_string(default.as_serialize()) {
+            details.push(("Default", Detail::code(json)));
+        }
+    }
+
+    if let Some(env) = prop.env {
+        details.push(("Environment", Detail::code(env)));
+    }
+
+    let flags: Vec<_> = prop.flags.iter_names().map(|x| x.0.to_ascii_lowercase()).collect();
+    if !flags.is_empty() {
+        details.push(("Flags", Detail::Text(flags.join(", "))));
+    }
+
+    if !prop.previous_names.is_empty() {
+        details.push((
+            "Previous names",
+            Detail::Code(prop.previous_names.iter().map(|&x| x.into()).collect()),
+        ));
+    }
+
+    if let Some(note) = prop.deprecated {
+        details.push(("Deprecated", Detail::Text(note.to_owned())));
+    }
+
+    details
+}
+
+/// Splits doc comment into paragraphs. Doc comments retain the space after `///`.
+fn paragraphs(description: &str) -> Vec<String> {
+    description
+        .split("\n\n")
+        .map(|x| x.lines().map(str::trim).collect::<Vec<_>>().join(" "))
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+#[cfg(feature = "jsonschema")]
+fn schema_json(prop: &PropertyInfo) -> Option<String> {
+    serde_json::to_string_pretty(prop.schema.as_ref()?).ok()
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
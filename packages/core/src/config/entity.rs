@@ -323,6 +323,9 @@ pub enum EntityUpdateError {
 
     #[error("Deserialization failed")]
     DeserializeFailed(#[from] erased_serde::Error),
+
+    #[error("Template validation failed: {0}")]
+    TemplateValidationFailed(Cow<'static, str>),
 }
 
 impl EntityData {
@@ -358,8 +361,13 @@ impl EntityData {
     pub(crate) fn __apply_value(&self, value: EntityValue) {
         debug_assert!(self.meta.type_id == value.as_any().type_id());
 
-        *self.value.write() = value;
-        self.version.fetch_add(1, Ordering::Release);
+        let (old, version) = {
+            let mut current = self.value.write();
+            let old = std::mem::replace(&mut *current, value.clone());
+            (old, self.version.fetch_add(1, Ordering::Release) + 1)
+        };
+
+        self.hook.on_value_applied(self, &old, &value, version);
     }
 
     /// Attempts to update the central value of a config entity by deserializing the provided input.
@@ -390,6 +398,27 @@ impl EntityData {
     ///
     /// * `de`: An instance of the deserializer used to update the central value.
     pub fn update_value_from<'a, T>(&self, de: T) -> Result<Validation, EntityUpdateError>
+    where
+        T: serde::Deserializer<'a>,
+    {
+        let (built, is_perfect) = self.build_value_from(de)?;
+        self.__apply_value(built);
+        Ok(is_perfect)
+    }
+
+    /// Deserializes and validates the input as same as [`Self::update_value_from`], without
+    /// applying it.
+    pub fn check_value_from<'a, T>(&self, de: T) -> Result<Validation, EntityUpdateError>
+    where
+        T: serde::Deserializer<'a>,
+    {
+        self.build_value_from(de).map(|(_, is_perfect)| is_perfect)
+    }
+
+    pub(crate) fn build_value_from<'a, T>(
+        &self,
+        de: T,
+    ) -> Result<(EntityValue, Validation), EntityUpdateError>
     where
         T: serde::Deserializer<'a>,
     {
@@ -404,8 +433,7 @@ impl EntityData {
                     Err(e) => return Err(EntityUpdateError::ValueValidationFailed(e)),
                 };
 
-                self.__apply_value(built);
-                Ok(is_perfect)
+                Ok((built, is_perfect))
             }
             Err(error) => {
                 tr::debug!(
@@ -442,4 +470,13 @@ impl EntityData {
 
 pub(crate) trait EntityEventHook: Send + Sync {
     fn on_value_changed(&self, data: &EntityData, silent: bool);
+
+    /// Called right after `old` was replaced with `new`, once the value lock is released.
+    fn on_value_applied(
+        &self,
+        data: &EntityData,
+        old: &EntityValue,
+        new: &EntityValue,
+        version: u64,
+    );
 }
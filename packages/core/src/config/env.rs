@@ -0,0 +1,52 @@
+//! Environment variables mapped onto every item by path.
+//!
+//! With prefix `MYAPP`, item `port` of the group at `net.server` is read from
+//! `MYAPP__NET__SERVER__PORT`. Path components and the item name are upper-cased, and characters
+//! other than ASCII alphanumerics are replaced with `_`. Values are parsed as JSON, falling back to
+//! plain strings, and must deserialize into the item type and pass its validation; invalid ones are
+//! skipped with a warning. Items flagged `NO_IMPORT` are not mapped.
+//!
+//! Enabled with [`Storage::set_env_prefix`](super::storage::Storage::set_env_prefix).
+
+use super::{cli, entity::Entity, group::GroupContext};
+use crate::shared::{archive::Archive, meta::MetaFlag};
+
+/// Name of the environment variable mapped onto item `name` of the group at `path`.
+pub fn var_name<'a>(
+    prefix: &str,
+    path: impl IntoIterator<Item = &'a str>,
+    name: &'a str,
+) -> String {
+    let mut var = prefix.to_owned();
+    for component in path.into_iter().chain([name]) {
+        var.push_str("__");
+        var.extend(component.chars().map(|c| match c {
+            c if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+            _ => '_',
+        }));
+    }
+    var
+}
+
+/// Collects the values of the group's environment variables into the archive.
+pub(crate) fn collect(prefix: &str, ctx: &GroupContext, archive: &mut Archive) {
+    for meta in ctx.sources.iter().map(|x| x.meta) {
+        if meta.flags.contains(MetaFlag::NO_IMPORT) {
+            continue;
+        }
+
+        let var = var_name(prefix, ctx.path.iter(), meta.name);
+        let Ok(raw) = std::env::var(&var) else { continue };
+
+        let parsed = cli::parse_value(meta, &raw, false).and_then(|(json, mut value)| {
+            meta.vtable.validate(value.as_any_mut()).map(|_| json).map_err(|e| e.into_owned())
+        });
+
+        match parsed {
+            Ok(json) => {
+                archive.find_or_create_path_mut(ctx.path.iter()).insert_value(meta.name, json)
+            }
+            Err(error) => tr::warn!(var, error, "Invalid environment variable, ignored"),
+        }
+    }
+}
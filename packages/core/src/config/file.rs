@@ -0,0 +1,382 @@
+//! File backed persistence of [`Storage`].
+//!
+//! [`FileBackend`] loads the file content into the storage on attachment, then keeps the file
+//! synchronized with the storage by saving it whenever any value is updated. Saves are debounced
+//! and performed on a dedicated thread, writing into a temporary file first and renaming it over
+//! the original one, therefore the file is never observed half-written even if the process
+//! crashes during the save.
+//!
+//! Saving patches the existing file content rather than rewriting it, therefore hand-written
+//! comments and key ordering survive. See [`Format::patch_document`].
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock, Weak},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use derive_setters::Setters;
+use parking_lot::{Condvar, Mutex};
+
+use crate::shared::{archive::Archive, GroupId, ItemId};
+
+use super::{
+    codec::{CodecError, Format},
+    storage::{Monitor, MonitorClosed, Storage},
+};
+
+/// Options of [`FileBackend`].
+#[derive(Debug, Clone, Setters)]
+#[non_exhaustive]
+pub struct FileBackendOptions {
+    /// File is saved once no more update is made during this period.
+    ///
+    /// Default is 500 ms.
+    pub debounce: Duration,
+
+    /// Upper bound of the period that continuous updates can defer the save.
+    ///
+    /// Default is 5 seconds.
+    pub max_delay: Duration,
+
+    /// Format of the file. If not specified, it's detected from the file extension.
+    #[setters(strip_option)]
+    pub format: Option<Format>,
+
+    /// Saves pending changes when the backend is dropped.
+    ///
+    /// Default is `true`.
+    pub save_on_drop: bool,
+}
+
+impl Default for FileBackendOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(500),
+            max_delay: Duration::from_secs(5),
+            format: None,
+            save_on_drop: true,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FileBackendError {
+    #[error("Can't detect file format from path {0:?}")]
+    UnknownFormat(PathBuf),
+
+    #[error("I/O error on {path:?}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Failed to parse {path:?}: {source}")]
+    Decode { path: PathBuf, source: CodecError },
+
+    #[error("Failed to serialize archive: {0}")]
+    Encode(#[from] CodecError),
+}
+
+/// Persists a [`Storage`] into single file. See module documentation.
+///
+/// The backend stops saving once dropped.
+pub struct FileBackend {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    storage: Storage,
+    path: PathBuf,
+    format: Format,
+    options: FileBackendOptions,
+
+    state: Mutex<SaveState>,
+    wake: Condvar,
+
+    /// Serializes save operations, and retains last written content to skip redundant writes.
+    last_written: Mutex<Option<String>>,
+}
+
+#[derive(Default)]
+struct SaveState {
+    /// Time of the first unsaved update.
+    dirty_since: Option<Instant>,
+
+    /// Time of the latest unsaved update.
+    last_update: Option<Instant>,
+
+    closed: bool,
+}
+
+/// Monitor which marks the backend dirty on storage updates.
+struct SaveTrigger(Weak<Shared>);
+
+impl FileBackend {
+    /// Attaches a file backend with default options. See [`FileBackend::attach_with`].
+    pub fn attach(storage: &Storage, path: impl Into<PathBuf>) -> Result<Self, FileBackendError> {
+        Self::attach_with(storage, path, Default::default())
+    }
+
+    /// Loads given file into the storage, then starts saving storage updates into it.
+    ///
+    /// Missing file is not an error; it'll be created on first save. However, if the file exists
+    /// and fails to be parsed, an error is returned without attaching, to not overwrite the file
+    /// content which may contain user's work.
+    pub fn attach_with(
+        storage: &Storage,
+        path: impl Into<PathBuf>,
+        options: FileBackendOptions,
+    ) -> Result<Self, FileBackendError> {
+        let path = path.into();
+        let format = options
+            .format
+            .or_else(|| Format::from_path(&path))
+            .ok_or_else(|| FileBackendError::UnknownFormat(path.clone()))?;
+
+        let loaded = match fs::read_to_string(&path) {
+            Ok(text) => {
+                let archive = format
+                    .decode(&text)
+                    .map_err(|source| FileBackendError::Decode { path: path.clone(), source })?;
+
+                storage.import(archive);
+                Some(text)
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(source) => return Err(FileBackendError::Io { path, source }),
+        };
+
+        let shared = Arc::new(Shared {
+            storage: storage.clone(),
+            path,
+            format,
+            options,
+            state: Default::default(),
+            wake: Default::default(),
+            last_written: Mutex::new(loaded),
+        });
+
+        storage.add_monitor(Arc::new(SaveTrigger(Arc::downgrade(&shared))));
+
+        let worker = std::thread::Builder::new()
+            .name("config-it-file-backend".into())
+            .spawn({
+                let shared = shared.clone();
+                move || shared.run()
+            })
+            .map_err(|source| FileBackendError::Io { path: shared.path.clone(), source })?;
+
+        Ok(Self { shared, worker: Some(worker) })
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.shared.path
+    }
+
+    /// Format of the backing file.
+    pub fn format(&self) -> Format {
+        self.shared.format
+    }
+
+    /// Returns true if there's any update which is not saved yet.
+    pub fn is_dirty(&self) -> bool {
+        self.shared.state.lock().dirty_since.is_some()
+    }
+
+    /// Saves pending changes immediately, if there's any.
+    pub fn flush(&self) -> Result<(), FileBackendError> {
+        if self.shared.state.lock().dirty_since.take().is_some() {
+            self.shared.save()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Saves current storage content immediately, regardless of pending changes.
+    pub fn save(&self) -> Result<(), FileBackendError> {
+        self.shared.state.lock().dirty_since = None;
+        self.shared.save()
+    }
+}
+
+impl Drop for FileBackend {
+    fn drop(&mut self) {
+        self.shared.state.lock().closed = true;
+        self.shared.wake.notify_all();
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Shared {
+    fn run(&self) {
+        let mut state = self.state.lock();
+
+        loop {
+            if state.closed {
+                if state.dirty_since.take().is_some() && self.options.save_on_drop {
+                    drop(state);
+                    self.save_logged();
+                }
+
+                break;
+            }
+
+            let (Some(first), Some(last)) = (state.dirty_since, state.last_update) else {
+                self.wake.wait(&mut state);
+                continue;
+            };
+
+            let deadline = (last + self.options.debounce).min(first + self.options.max_delay);
+            if Instant::now() < deadline {
+                self.wake.wait_until(&mut state, deadline);
+                continue;
+            }
+
+            state.dirty_since = None;
+            parking_lot::MutexGuard::unlocked(&mut state, || self.save_logged());
+        }
+    }
+
+    fn save_logged(&self) {
+        if let Err(error) = self.save() {
+            tr::error!(%error, path = ?self.path, "failed to save storage");
+        }
+    }
+
+    fn save(&self) -> Result<(), FileBackendError> {
+        let mut last_written = self.last_written.lock();
+        let text = self.render(self.storage.exporter().collect())?;
+
+        if last_written.as_ref() == Some(&text) {
+            return Ok(());
+        }
+
+        // Register before writing, so that watchers never observe unregistered content.
+        register_self_write(&self.path, text.as_bytes());
+        write_atomic(&self.path, text.as_bytes())
+            .map_err(|source| FileBackendError::Io { path: self.path.clone(), source })?;
+
+        *last_written = Some(text);
+        Ok(())
+    }
+
+    /// Renders the archive by patching the current file content, so that comments and ordering
+    /// written by hand are kept.
+    fn render(&self, mut archive: Archive) -> Result<String, FileBackendError> {
+        let original = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(self.format.encode(&archive)?)
+            }
+            Err(source) => return Err(FileBackendError::Io { path: self.path.clone(), source }),
+        };
+
+        let Ok(base) = self.format.decode(&original) else {
+            tr::warn!(path = ?self.path, "Overwriting unparsable file");
+            return Ok(self.format.encode(&archive)?);
+        };
+
+        let patch = base.create_patch(&mut archive);
+        // Doc comments retain the space after `///`.
+        let describe = |path: &[&str], name: &str| {
+            let description = self.storage.item_description(path.iter().copied(), name)?;
+            Some(description.lines().map(str::trim).collect::<Vec<_>>().join("\n"))
+        };
+
+        Ok(self.format.patch_document(&original, &patch, &describe)?)
+    }
+
+    fn mark_dirty(&self) {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+
+        state.dirty_since.get_or_insert(now);
+        state.last_update = Some(now);
+        self.wake.notify_all();
+    }
+}
+
+impl Monitor for SaveTrigger {
+    fn should_dispose(&self) -> bool {
+        self.0.strong_count() == 0
+    }
+
+    fn group_removed(&self, _: GroupId) -> Result<(), MonitorClosed> {
+        self.0.upgrade().ok_or(MonitorClosed)?.mark_dirty();
+        Ok(())
+    }
+
+    fn entity_value_updated(&self, _: GroupId, _: ItemId) -> Result<(), MonitorClosed> {
+        self.0.upgrade().ok_or(MonitorClosed)?.mark_dirty();
+        Ok(())
+    }
+}
+
+/* ------------------------------------ Self Write Registry ----------------------------------- */
+
+/// Hashes of the latest content written by [`FileBackend`]s, keyed by normalized path. File
+/// watchers refer to this to ignore changes made by autosave.
+fn self_writes() -> &'static Mutex<HashMap<PathBuf, u64>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<PathBuf, u64>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+fn register_self_write(path: &Path, content: &[u8]) {
+    self_writes().lock().insert(normalize_path(path), content_hash(content));
+}
+
+/// Checks if given content of the file was written by any [`FileBackend`] of this process.
+pub(crate) fn is_self_write(path: &Path, content: &[u8]) -> bool {
+    self_writes().lock().get(&normalize_path(path)) == Some(&content_hash(content))
+}
+
+pub(crate) fn content_hash(content: &[u8]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Resolves the parent directory and joins the file name, so that the key of a file doesn't
+/// change whether it exists or not at the moment.
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else { return path };
+
+    fs::canonicalize(dir).map(|dir| dir.join(name)).unwrap_or(path)
+}
+
+/// Writes the content into a temporary file next to the destination, then renames it over the
+/// destination.
+fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let dir = path.parent().filter(|x| !x.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path.file_name().ok_or(io::ErrorKind::InvalidInput)?;
+
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(".tmp");
+    let temp_path = dir.join(temp_name);
+
+    fs::create_dir_all(dir)?;
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
@@ -1,5 +1,7 @@
 use bitfield::bitfield;
+use parking_lot::{RwLock, RwLockWriteGuard};
 use std::any::{Any, TypeId};
+use std::borrow::Cow;
 use std::iter::zip;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Weak};
@@ -7,7 +9,7 @@ use strseq::SharedStringSequence;
 
 use crate::shared::GroupId;
 
-use super::entity::{Entity, EntityData, EntityValue, PropertyInfo};
+use super::entity::{Entity, EntityData, EntityValue, PropertyInfo, Validation, ValidationResult};
 use super::noti;
 
 ///
@@ -39,6 +41,41 @@ pub trait Template: Clone + 'static {
         let data = self.elem_at_mut__(index);
         meta.vtable.clone_in_place(value, data);
     }
+
+    /// Cross-field validator of the whole template, which is given with the struct level
+    /// `#[config(validate = "<function_name>")]` attribute.
+    #[doc(hidden)]
+    fn validator__() -> Option<ValidateTemplateFn<Self>> {
+        None
+    }
+}
+
+/// Signature of function to validate the whole config template. Like field validators, it may
+/// adjust the candidate, or reject it.
+pub type ValidateTemplateFn<T> = fn(&mut T) -> ValidationResult;
+
+/// Type erased template validator, which validates the values of every item in order.
+pub(crate) type ValidateCandidateFn = fn(&mut [EntityValue]) -> ValidationResult;
+
+/// Assembles the candidate template from item values, and validates it. Adjusted values are
+/// written back.
+pub(crate) fn validate_candidate<T: Template>(values: &mut [EntityValue]) -> ValidationResult {
+    let Some(validate) = T::validator__() else { return Ok(Validation::Valid) };
+    let mut candidate = T::default_config();
+
+    for (prop, value) in zip(T::props__(), &*values) {
+        candidate.update_elem_at__(prop.index, value.as_any(), prop);
+    }
+
+    let validation = validate(&mut candidate)?;
+
+    if validation == Validation::Modified {
+        for (prop, value) in zip(T::props__(), values) {
+            prop.vtable.clone_in_place(candidate.elem_at_mut__(prop.index), value.as_any_mut());
+        }
+    }
+
+    Ok(validation)
 }
 
 /* --------------------------------------- Local Property --------------------------------------- */
@@ -110,12 +147,91 @@ pub struct GroupContext {
     /// A channel for receiving update notifications from the
     /// backend, enabling the group to respond to external changes or synchronize its state.
     pub(crate) update_receiver_channel: noti::Receiver,
+
+    /// Cross-field validator of the template, if the template has one.
+    pub(crate) validate_candidate: Option<ValidateCandidateFn>,
+
+    /// Held exclusively while applying multiple values at once, so that [`Group::update`] never
+    /// observes a half-applied set of changes.
+    pub(crate) commit_lock: RwLock<()>,
+}
+
+impl GroupContext {
+    /// Validates the candidate made of current item values and given `changes`, which are pairs
+    /// of source index and new value, with the template validator.
+    ///
+    /// Values adjusted by the validator replace the corresponding entries of `changes`, and other
+    /// items adjusted by it are appended. Returns the indices of every adjusted item.
+    pub(crate) fn check_candidate(
+        &self,
+        changes: &mut Vec<(usize, EntityValue)>,
+    ) -> Result<Vec<usize>, Cow<'static, str>> {
+        let Some(validate) = self.validate_candidate else { return Ok(Vec::new()) };
+
+        let mut values: Vec<_> = self.sources.iter().map(|x| x.property_value().1).collect();
+        for (index, value) in changes.iter() {
+            values[*index] = value.clone();
+        }
+
+        let before = values.clone();
+        if validate(&mut values)? == Validation::Valid {
+            return Ok(Vec::new());
+        }
+
+        // Values are compared by their serialized form, since entities aren't comparable.
+        let json = |x: &EntityValue| serde_json::to_value(x.as_serialize()).ok();
+        let mut adjusted = Vec::new();
+
+        for (index, value) in values.into_iter().enumerate() {
+            if json(&value) == json(&before[index]) {
+                continue;
+            }
+
+            adjusted.push(index);
+            match changes.iter_mut().find(|x| x.0 == index) {
+                Some(change) => change.1 = value,
+                None => changes.push((index, value)),
+            }
+        }
+
+        Ok(adjusted)
+    }
+
+    /// Applies every change at once, under the commit lock acquired by the caller. The lock is
+    /// released before other groups sharing this context are notified, only once, after all
+    /// values are applied.
+    pub(crate) fn apply_changes(
+        &self,
+        guard: RwLockWriteGuard<'_, ()>,
+        changes: Vec<(usize, EntityValue)>,
+        notify: bool,
+    ) {
+        let indices: Vec<_> = changes.iter().map(|x| x.0).collect();
+
+        for (index, value) in changes {
+            self.sources[index].__apply_value(value);
+        }
+
+        drop(guard);
+
+        // Touching silently still reports each item to monitors; only the last one bumps the
+        // group version.
+        for (order, &index) in indices.iter().enumerate() {
+            self.sources[index].touch(notify && order + 1 == indices.len());
+        }
+    }
 }
 
 mod monitor {
     //! Exposed APIs to control over entities
 
-    use crate::{config::noti, shared::ItemId};
+    use crate::{
+        config::{
+            entity::{EntityUpdateError, Validation},
+            noti,
+        },
+        shared::ItemId,
+    };
 
     impl super::GroupContext {
         /// Finds an item with the given `item_id` in the group's sources.
@@ -140,6 +256,43 @@ mod monitor {
         pub fn entities(&self) -> &[super::EntityData] {
             &self.sources
         }
+
+        /// Deserializes a new value of `item` from `de`, and commits it with notification. The
+        /// value is checked with the field validator, then with the template validator together
+        /// with the other items, as [`super::Group::try_commit_elem`] does.
+        ///
+        /// # Panics
+        ///
+        /// If `item` doesn't belong to this group.
+        pub fn commit_value_from<'a, D>(
+            &self,
+            item: &super::EntityData,
+            de: D,
+        ) -> Result<Validation, EntityUpdateError>
+        where
+            D: serde::Deserializer<'a>,
+        {
+            let index = self
+                .sources
+                .iter()
+                .position(|x| std::ptr::eq(x, item))
+                .expect("item doesn't belong to this group");
+
+            let (value, mut validation) = item.build_value_from(de)?;
+
+            let guard = self.commit_lock.write();
+            let mut changes = vec![(index, value)];
+            let adjusted = self
+                .check_candidate(&mut changes)
+                .map_err(EntityUpdateError::TemplateValidationFailed)?;
+
+            if !adjusted.is_empty() {
+                validation = Validation::Modified;
+            }
+
+            self.apply_changes(guard, changes, true);
+            Ok(validation)
+        }
     }
 }
 
@@ -219,6 +372,57 @@ impl Default for PropLocalContext {
 /// Type alias for broadcast receiver
 pub type WatchUpdate = noti::Receiver;
 
+/// Stream of new values of a single group item, created by [`Group::watch_field`].
+///
+/// Values changed several times between polls are coalesced into the latest one. The stream ends
+/// once the group is unregistered from its storage.
+pub struct WatchField<U> {
+    origin: Arc<GroupContext>,
+    index: usize,
+    version: u64,
+    rx: noti::Receiver,
+    _value: std::marker::PhantomData<fn() -> U>,
+}
+
+impl<U> Drop for WatchField<U> {
+    fn drop(&mut self) {
+        // Waker may pin the polling task; it must not outlive the stream.
+        self.rx.cancel_recv();
+    }
+}
+
+impl<U: Clone + 'static> futures_core::Stream for WatchField<U> {
+    type Item = U;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<U>> {
+        use std::task::Poll;
+        let this = self.get_mut();
+
+        loop {
+            let source = &this.origin.sources[this.index];
+
+            // Version is read before the value, thus a value is never missed; at worst, it may be
+            // yielded twice.
+            let version = source.version();
+            if version != this.version {
+                this.version = version;
+                let (_, value) = source.property_value();
+                let value = value.as_any().downcast_ref::<U>().unwrap().clone();
+                return Poll::Ready(Some(value));
+            }
+
+            match this.rx.poll_recv(cx) {
+                Poll::Ready(Ok(())) => continue,
+                Poll::Ready(Err(_)) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
 impl<T: Template> Group<T> {
     #[doc(hidden)]
     pub(crate) fn create_with__(
@@ -283,6 +487,7 @@ impl<T: Template> Group<T> {
             "Logic Error: The set was not correctly initialized!"
         );
 
+        let _guard = self.origin.commit_lock.read();
         for ((index, local), source) in zip(zip(0..local.len(), &mut *local), &*self.origin.sources)
         {
             // Check if the given config entity has any updates.
@@ -367,9 +572,22 @@ impl<T: Template> Group<T> {
     /// * `prop`: The element containing the changes to be committed.
     /// * `notify`: If set to `true`, it triggers other groups that share the same context to be
     ///   notified of this change.
+    ///
+    /// If the template has a cross-field validator which rejects the change, nothing is committed.
+    /// Use [`Group::try_commit_elem`] to find out.
     pub fn commit_elem<U: Clone + Entity>(&self, prop: &U, notify: bool) {
+        if let Err(error) = self.try_commit_elem(prop, notify) {
+            tr::warn!(%error, "Commit rejected by template validator");
+        }
+    }
+
+    /// Same as [`Group::commit_elem`], but returns the verdict of the template validator. If the
+    /// validator adjusts any value, adjusted values are committed, and the local copy receives them
+    /// on the next [`Group::update`].
+    pub fn try_commit_elem<U: Clone + Entity>(&self, prop: &U, notify: bool) -> ValidationResult {
         // Replace source argument with created pointer
-        let elem = &(*self.origin.sources)[self.get_index_by_ptr(prop).unwrap()];
+        let index = self.get_index_by_ptr(prop).unwrap();
+        let elem = &(*self.origin.sources)[index];
 
         // Determine if the value type supports copy operations
         let impl_copy = elem.meta.vtable.implements_copy();
@@ -378,10 +596,72 @@ impl<T: Template> Group<T> {
         // Proper management of this check is essential to guarantee the safety of this operation.
         let new_value = unsafe { EntityValue::from_value(prop.clone(), impl_copy) };
 
-        // Apply the new value to the element
-        elem.__apply_value(new_value);
-        // Update and potentially notify other contexts of the change
-        elem.touch(notify);
+        // Validation and application are done under the same lock, so that concurrent commits of
+        // other fields can't invalidate the candidate in between.
+        let guard = self.origin.commit_lock.write();
+        let mut changes = vec![(index, new_value)];
+        let adjusted = self.origin.check_candidate(&mut changes)?;
+
+        // Apply the new value to the element, and potentially notify other contexts of the change
+        self.origin.apply_changes(guard, changes, notify);
+
+        Ok(if adjusted.is_empty() { Validation::Valid } else { Validation::Modified })
+    }
+
+    /// Commits every element whose local value differs from the shared one, as a single change.
+    /// Other groups sharing the same context observe either all of them or none on
+    /// [`Group::update`], and are notified only once if `notify` is set.
+    ///
+    /// Each value is checked with its field validator, then the whole set with the template
+    /// validator. If any of them rejects, nothing is committed. Values adjusted by validators are
+    /// committed, and the local copy receives them on the next [`Group::update`].
+    pub fn commit_all(&mut self, notify: bool) -> ValidationResult {
+        // Values are compared by their serialized form, since entities aren't comparable.
+        let json = |x: &EntityValue| serde_json::to_value(x.as_serialize()).ok();
+        let mut changes = Vec::new();
+        let mut validation = Validation::Valid;
+
+        let guard = self.origin.commit_lock.write();
+        for (index, source) in self.origin.sources.iter().enumerate() {
+            let vtable = &source.meta.vtable;
+            let mut value = vtable.create_default();
+            vtable.clone_in_place(self.__body.elem_at_mut__(index), value.as_any_mut());
+
+            if json(&value) == json(&source.property_value().1) {
+                continue;
+            }
+
+            if vtable.validate(value.as_any_mut())? == Validation::Modified {
+                validation = Validation::Modified;
+            }
+
+            changes.push((index, value));
+        }
+
+        if changes.is_empty() {
+            return Ok(validation);
+        }
+
+        if !self.origin.check_candidate(&mut changes)?.is_empty() {
+            validation = Validation::Modified;
+        }
+
+        self.origin.apply_changes(guard, changes, notify);
+        Ok(validation)
+    }
+
+    /// Edits the local copy with `edit`, then commits every changed element at once with
+    /// notification. See [`Group::commit_all`].
+    ///
+    /// ```ignore
+    /// server.transaction(|x| {
+    ///     x.host = "remote".into();
+    ///     x.port = 8080;
+    /// })?;
+    /// ```
+    pub fn transaction(&mut self, edit: impl FnOnce(&mut T)) -> ValidationResult {
+        edit(&mut self.__body);
+        self.commit_all(true)
     }
 
     /// Notify changes to core context, without actual content change. This will trigger the entire
@@ -401,6 +681,26 @@ impl<T: Template> Group<T> {
         self.origin.watch_update()
     }
 
+    /// Creates a stream of new values of given element, which yields whenever a change of it is
+    /// committed with notification. The current value is not yielded.
+    ///
+    /// ```ignore
+    /// let mut ports = group.watch_field(&group.port);
+    /// while let Some(port) = ports.next().await {
+    ///     rebind(port);
+    /// }
+    /// ```
+    pub fn watch_field<U: Clone + 'static>(&self, prop: *const U) -> WatchField<U> {
+        let index = self.get_index_by_ptr(prop).unwrap();
+        WatchField {
+            version: self.origin.sources[index].version(),
+            origin: self.origin.clone(),
+            index,
+            rx: self.origin.watch_update(),
+            _value: Default::default(),
+        }
+    }
+
     /// Mark all elements dirty. Next call to [`Group::update()`] may not return true if there
     /// wasn't any actual update, however, every call to [`Group::clear_flag()`] for
     /// each elements will return true.
@@ -432,6 +732,12 @@ impl<T: Template> Group<T> {
     pub fn path(&self) -> &SharedStringSequence {
         &self.origin.path
     }
+
+    /// Retrieves the ID of the group context, which is shared by every instance of this group.
+    /// Monitors identify groups with this value.
+    pub fn group_id(&self) -> GroupId {
+        self.origin.group_id
+    }
 }
 
 impl<T: Template> std::ops::Deref for Group<T> {
@@ -0,0 +1,86 @@
+//! Outcome of an import, which is retrieved by calling `apply()` on the import task returned by
+//! [`Storage::import`](super::storage::Storage::import).
+
+/// Lists what happened to each archive value during an import.
+///
+/// Only the values which were actually loaded onto live groups are listed; when the import is
+/// applied as a patch, which is the default, values which didn't change are not. Values of
+/// categories without a live group are listed as unknown; they're still kept in the archive cache,
+/// and are loaded once the group is created.
+#[derive(Debug, Default, Clone)]
+pub struct ImportReport {
+    /// Every item which the import tried to update.
+    pub items: Vec<ItemReport>,
+
+    /// Archive values which matched no item of any live group, including the ones under
+    /// categories without a live group.
+    pub unknown: Vec<UnknownKey>,
+
+    /// True if a strict import found any error, thus nothing was applied. Items then tell the
+    /// outcome each value would have had.
+    pub aborted: bool,
+}
+
+/// Import result of single item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemReport {
+    /// Path of the group.
+    pub path: Vec<String>,
+
+    /// Name of the item.
+    pub name: &'static str,
+
+    pub outcome: ItemOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemOutcome {
+    /// Value was applied as-is.
+    Applied,
+
+    /// Value was applied, after being modified by the validator to satisfy its constraint.
+    Clamped,
+
+    /// Value was removed from the archive, therefore the item was reset to its template default.
+    Reset,
+
+    /// Value failed to be deserialized or validated. The item retains its previous value.
+    Rejected(String),
+
+    /// Secret value couldn't be decrypted. Its stored text is loaded as-is if possible, which
+    /// usually isn't useful.
+    DecryptFailed(String),
+
+    /// Item is flagged `no_import`, thus the value was ignored.
+    Skipped,
+}
+
+/// Archive value which doesn't correspond to any item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// Path of the group.
+    pub path: Vec<String>,
+
+    /// Key of the value.
+    pub name: String,
+}
+
+impl ImportReport {
+    /// Returns true if every value was loaded without any error.
+    pub fn is_clean(&self) -> bool {
+        self.unknown.is_empty()
+            && self.items.iter().all(|x| {
+                !matches!(x.outcome, ItemOutcome::Rejected(_) | ItemOutcome::DecryptFailed(_))
+            })
+    }
+
+    /// Finds the report of given item.
+    pub fn find<'a>(
+        &self,
+        path: impl IntoIterator<Item = &'a str>,
+        name: &str,
+    ) -> Option<&ItemOutcome> {
+        let path: Vec<_> = path.into_iter().collect();
+        self.items.iter().find(|x| x.name == name && x.path == path).map(|x| &x.outcome)
+    }
+}
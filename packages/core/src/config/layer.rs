@@ -0,0 +1,118 @@
+//! Layered configuration sources.
+//!
+//! Each [`Layer`] of a [`Storage`](super::storage::Storage) holds its own [`Archive`]. The
+//! effective value of an item is taken from the highest layer which defines it, and falls back to
+//! the template default if none of them does. Replacing or clearing a layer only re-applies the
+//! items whose effective value actually changed, so clearing a runtime override restores the value
+//! supplied by the layer below.
+//!
+//! Values of the transient layers, from [`Layer::Environment`] upwards, are not written into the
+//! archive cache, thus are never exported; items supplied by them are exported with the value
+//! they'd have without those layers.
+
+use crate::shared::archive::Archive;
+
+/// Source of configuration values, in ascending order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    /// Compiled-in defaults. Template defaults are implicitly part of this layer.
+    Defaults,
+
+    /// System wide configuration file.
+    System,
+
+    /// Per-user configuration file.
+    User,
+
+    /// Values collected from environment variables.
+    Environment,
+
+    /// Values given as command-line arguments. See [`super::cli`].
+    CommandLine,
+
+    /// Overrides made at runtime.
+    Override,
+}
+
+impl Layer {
+    /// Every layer, from the lowest to the highest precedence.
+    pub const ALL: [Layer; 6] = [
+        Layer::Defaults,
+        Layer::System,
+        Layer::User,
+        Layer::Environment,
+        Layer::CommandLine,
+        Layer::Override,
+    ];
+
+    /// Whether the values of this layer are transient. They take precedence over the
+    /// configuration files, but are never written back into them on export.
+    pub fn is_transient(self) -> bool {
+        self >= Layer::Environment
+    }
+}
+
+/// Set of layer archives owned by the storage.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct LayerStack {
+    layers: [Option<Archive>; Layer::ALL.len()],
+}
+
+impl LayerStack {
+    pub fn get(&self, layer: Layer) -> Option<&Archive> {
+        self.layers[layer as usize].as_ref()
+    }
+
+    #[cfg(feature = "crypt")]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Layer, &mut Archive)> {
+        Layer::ALL.into_iter().zip(&mut self.layers).filter_map(|(l, x)| Some((l, x.as_mut()?)))
+    }
+
+    /// Replaces the content of given layer, returning the previous one.
+    pub fn replace(&mut self, layer: Layer, archive: Option<Archive>) -> Option<Archive> {
+        std::mem::replace(&mut self.layers[layer as usize], archive)
+    }
+
+    /// Merges the archive onto the content of given layer.
+    pub fn merge(&mut self, layer: Layer, archive: Archive) {
+        let slot = &mut self.layers[layer as usize];
+        *slot = Some(slot.take().unwrap_or_default().merge(archive));
+    }
+
+    /// Merges every layer which isn't transient, in order of precedence.
+    pub fn persistent(&self) -> Archive {
+        self.merged(|x| !x.is_transient())
+    }
+
+    /// Effective content seen by the groups: the archive cache, which already holds the
+    /// persistent layers, overlaid with every transient layer.
+    pub fn view(&self, cache: &Archive) -> Archive {
+        cache.clone().merge(self.merged(Layer::is_transient))
+    }
+
+    /// Merges the categories at `path` of every transient layer.
+    pub fn transient_at<'a>(&self, path: impl IntoIterator<Item = &'a str> + Clone) -> Archive {
+        (Layer::ALL.into_iter().filter(|x| x.is_transient()))
+            .filter_map(|x| self.get(x)?.find_path(path.clone()))
+            .fold(Archive::default(), |acc, x| acc.merge(x.clone()))
+    }
+
+    fn merged(&self, filter: impl Fn(Layer) -> bool) -> Archive {
+        (Layer::ALL.into_iter().zip(&self.layers))
+            .filter_map(|(layer, x)| x.as_ref().filter(|_| filter(layer)))
+            .fold(Archive::default(), |acc, x| acc.merge(x.clone()))
+    }
+
+    /// Finds the highest layer which defines the value `name` under `path`.
+    pub fn source_of<'a>(
+        &self,
+        path: impl IntoIterator<Item = &'a str> + Clone,
+        name: &str,
+    ) -> Option<Layer> {
+        Layer::ALL.into_iter().rev().find(|&layer| {
+            self.get(layer)
+                .and_then(|x| x.find_path(path.clone()))
+                .is_some_and(|node| node.values.contains_key(name))
+        })
+    }
+}
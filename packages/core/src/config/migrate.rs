@@ -0,0 +1,63 @@
+//! Schema versioning of archives.
+//!
+//! Schema version of a storage is the highest version among its registered migrations, or zero if
+//! there's none. It's written into every exported archive under the root value
+//! [`SCHEMA_VERSION_KEY`]. Before an archive of older version is imported, every migration of
+//! higher version is applied to it in order. Archives without the version are considered as
+//! version zero.
+//!
+//! Migrations are registered with [`Storage::add_migration`] and [`Storage::add_group_migration`].
+//!
+//! [`Storage::add_migration`]: super::storage::Storage::add_migration
+//! [`Storage::add_group_migration`]: super::storage::Storage::add_group_migration
+
+use crate::shared::archive::Archive;
+
+/// Root value key of the archive, which stores its schema version.
+pub const SCHEMA_VERSION_KEY: &str = "$schema-version";
+
+/// Transforms an archive of the previous schema version.
+pub type MigrateFn = Box<dyn Fn(&mut Archive) + Send + Sync>;
+
+/// Registered migrations, sorted by version.
+#[derive(Default)]
+pub(crate) struct Migrations {
+    steps: Vec<(u32, MigrateFn)>,
+}
+
+impl Migrations {
+    /// Migrations of the same version run in order of registration.
+    pub fn add(&mut self, version: u32, migrate: MigrateFn) {
+        let at = self.steps.partition_point(|x| x.0 <= version);
+        self.steps.insert(at, (version, migrate));
+    }
+
+    pub fn current(&self) -> u32 {
+        self.steps.last().map_or(0, |x| x.0)
+    }
+
+    /// Upgrades the archive to the current version.
+    pub fn upgrade(&self, archive: &mut Archive) {
+        let current = self.current();
+        let version = version_of(archive);
+
+        if version > current {
+            tr::warn!(version, current, "Archive is newer than the storage schema");
+            return;
+        }
+
+        for (_, migrate) in self.steps.iter().filter(|x| x.0 > version) {
+            migrate(archive);
+        }
+
+        if current > 0 {
+            archive.insert_value(SCHEMA_VERSION_KEY, current.into());
+        }
+    }
+}
+
+/// Reads the schema version of the archive.
+pub fn version_of(archive: &Archive) -> u32 {
+    let version = archive.get_value(SCHEMA_VERSION_KEY).and_then(|x| x.as_u64());
+    version.map_or(0, |x| x.try_into().unwrap_or(u32::MAX))
+}
@@ -1,18 +1,34 @@
+pub mod change;
+pub mod cli;
+pub mod codec;
+#[cfg(feature = "crypt")]
+pub mod crypt;
+pub mod docs;
 pub mod entity;
+pub mod env;
+pub mod file;
 pub mod group;
+pub mod import;
+pub mod layer;
+pub mod migrate;
 pub mod noti;
+#[cfg(feature = "jsonschema")]
+pub mod schema;
 pub mod storage;
+pub mod watch;
 
 /// Macro helper
 #[doc(hidden)]
 pub mod __lookup {
+    /// Zero-sized type probe, which is used to dispatch type-dependent lookups in generated code.
     #[doc(hidden)]
-    pub fn __default_ref_ptr<T>() -> &'static T {
-        // SAFETY: We won't do anything with this reference.
-        unsafe {
-            #[allow(deref_nullptr)]
-            &*std::ptr::null()
-        }
+    pub struct TypeProbe<T>(std::marker::PhantomData<T>);
+
+    #[doc(hidden)]
+    pub fn __default_ref_ptr<T>() -> &'static TypeProbe<T> {
+        // SAFETY: `TypeProbe<T>` is zero-sized, therefore any well-aligned, non-null pointer is a
+        // valid reference to it.
+        unsafe { std::ptr::NonNull::dangling().as_ref() }
     }
 
     #[allow(dead_code)]
@@ -25,12 +41,18 @@ pub mod __lookup {
     }
 
     #[cfg(feature = "jsonschema")]
-    impl<T: schemars::JsonSchema> HasSchema for T {
+    impl<T: schemars::JsonSchema> HasSchema for TypeProbe<T> {
         fn get_schema(&self) -> Option<crate::Schema> {
             Some(schemars::schema_for!(T))
         }
     }
 
+    /// Serializes `min`, `max` and `one_of` arguments into the schema.
+    #[cfg(feature = "jsonschema")]
+    pub fn __schema_value(value: impl serde::Serialize) -> serde_json::Value {
+        serde_json::to_value(value).unwrap_or_default()
+    }
+
     #[cfg(feature = "jsonschema")]
     pub trait NoSchema {
         fn get_schema(&self) -> Option<crate::Schema> {
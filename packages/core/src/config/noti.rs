@@ -1,7 +1,10 @@
 //! A naive 'watch' implementation for monitoring updates
 
 use std::{
-    sync::{Arc, Weak},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Weak,
+    },
     task::{Poll, Waker},
 };
 
@@ -11,8 +14,20 @@ use smallvec::SmallVec;
 #[derive(Debug, Clone)]
 pub struct Sender(Arc<Mutex<Inner>>);
 
-#[derive(Debug, Clone)]
-pub struct Receiver(usize, Weak<Mutex<Inner>>);
+/// Fence, channel, and the waiter ID which is unique per receiver instance.
+#[derive(Debug)]
+pub struct Receiver(usize, Weak<Mutex<Inner>>, usize);
+
+impl Clone for Receiver {
+    fn clone(&self) -> Self {
+        Self(self.0, self.1.clone(), next_waiter_id())
+    }
+}
+
+fn next_waiter_id() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
 
 impl Default for Sender {
     fn default() -> Self {
@@ -32,7 +47,8 @@ impl Sender {
     }
 
     pub fn receiver(&self, fresh: bool) -> Receiver {
-        Receiver(if fresh { 0 } else { self.0.lock().fence }, Arc::downgrade(&self.0))
+        let fence = if fresh { 0 } else { self.0.lock().fence };
+        Receiver(fence, Arc::downgrade(&self.0), next_waiter_id())
     }
 }
 
@@ -42,6 +58,13 @@ struct Inner {
     waiters: SmallVec<[(usize, Waker); 4]>,
 }
 
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // Lets pending waiters observe the closed channel.
+        self.waiters.drain(..).for_each(|x| x.1.wake());
+    }
+}
+
 impl Receiver {
     pub fn invalidate(&mut self) {
         self.0 = 0;
@@ -61,9 +84,39 @@ impl Receiver {
         }
     }
 
-    pub fn recv(&mut self) -> Wait {
+    pub fn recv(&mut self) -> Wait<'_> {
         Wait { rx: self, state: WaitState::Created }
     }
+
+    /// Polls for an update without borrowing a [`Wait`]. Until there's one, the waker of `cx` is
+    /// registered to be woken by the next notification, or by disposal of the sender.
+    pub fn poll_recv(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), WaitError>> {
+        let Some(inner) = self.1.upgrade() else { return Poll::Ready(Err(WaitError::Closed)) };
+        let mut inner = inner.lock();
+
+        if inner.fence != self.0 {
+            self.0 = inner.fence;
+            return Poll::Ready(Ok(()));
+        }
+
+        let id = self.2;
+        match inner.waiters.iter_mut().find(|x| x.0 == id) {
+            Some(waiter) => waiter.1.clone_from(cx.waker()),
+            None => inner.waiters.push((id, cx.waker().clone())),
+        }
+
+        Poll::Pending
+    }
+
+    /// Removes the waker registered by [`Receiver::poll_recv`], if any.
+    pub fn cancel_recv(&self) {
+        let Some(inner) = self.1.upgrade() else { return };
+        let waiters = &mut inner.lock().waiters;
+
+        if let Some(idx) = waiters.iter().position(|x| x.0 == self.2) {
+            waiters.swap_remove(idx);
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -118,7 +171,7 @@ impl<'a> Wait<'a> {
     }
 
     fn get_id(&self) -> usize {
-        self.rx as *const _ as usize
+        self.rx.2
     }
 }
 
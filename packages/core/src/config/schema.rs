@@ -0,0 +1,155 @@
+//! JSON Schema of whole archive files, for editor validation and completion.
+//!
+//! Each group becomes a nested object under its category keys, which are named after the
+//! [`CategoryRule`]. Each item is described by the schema of its type, along with its doc comment,
+//! template default and `min`/`max`/`one_of` constraints.
+//!
+//! ```ignore
+//! let schema = storage.archive_schema();
+//! std::fs::write("config.schema.json", serde_json::to_string_pretty(&schema)?)?;
+//! ```
+
+use std::collections::BTreeMap;
+
+use compact_str::CompactString;
+use schemars::schema::{InstanceType, Metadata, RootSchema, SchemaObject, SubschemaValidation};
+
+use super::{
+    entity::{Entity, PropertyInfo},
+    group::Template,
+    storage::Storage,
+};
+use crate::shared::{archive::CategoryRule, meta::MetaFlag};
+
+/// Builds a JSON Schema of the archive, from live groups or templates.
+#[derive(Default)]
+pub struct ArchiveSchema {
+    rule: CategoryRule<'static>,
+    root: Node,
+}
+
+#[derive(Default)]
+struct Node {
+    props: Vec<&'static PropertyInfo>,
+    children: BTreeMap<String, Node>,
+}
+
+impl ArchiveSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describes every live group of the storage.
+    pub fn from_storage(storage: &Storage) -> Self {
+        let mut this = Self::new();
+        for ctx in storage.group_contexts() {
+            this.add_props(ctx.path.iter(), ctx.sources.iter().map(|x| x.meta));
+        }
+        this
+    }
+
+    /// Describes template `T` at given path, which doesn't need to be instantiated.
+    pub fn add_template<T: Template>(
+        &mut self,
+        path: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> &mut Self {
+        self.add_props(path, T::props__().iter());
+        self
+    }
+
+    /// Category key convention of the described files. Defaults to the `~` prefix.
+    pub fn category_rule(&mut self, rule: CategoryRule<'static>) -> &mut Self {
+        self.rule = rule;
+        self
+    }
+
+    fn add_props(
+        &mut self,
+        path: impl IntoIterator<Item = impl AsRef<str>>,
+        props: impl IntoIterator<Item = &'static PropertyInfo>,
+    ) {
+        let node = path.into_iter().fold(&mut self.root, |node, key| {
+            node.children.entry(key.as_ref().into()).or_default()
+        });
+        node.props = props.into_iter().collect();
+    }
+
+    /// Builds the schema. Definitions of every item type are merged into the root.
+    pub fn build(&self) -> RootSchema {
+        let mut root = RootSchema {
+            meta_schema: schemars::gen::SchemaSettings::draft07().meta_schema,
+            ..Default::default()
+        };
+
+        let mut key = CompactString::default();
+        root.schema = self.node_schema(&self.root, &mut root, &mut key);
+        root
+    }
+
+    fn node_schema(
+        &self,
+        node: &Node,
+        root: &mut RootSchema,
+        key: &mut CompactString,
+    ) -> SchemaObject {
+        let mut schema =
+            SchemaObject { instance_type: Some(InstanceType::Object.into()), ..Default::default() };
+
+        for &prop in node.props.iter().filter(|x| !x.flags.contains(MetaFlag::NO_IMPORT)) {
+            let item = item_schema(prop, root);
+            schema.object().properties.insert(prop.name.into(), item.into());
+        }
+
+        for (name, child) in &node.children {
+            let child = self.node_schema(child, root, key);
+            self.rule.make_category(name, key);
+            schema.object().properties.insert(key.to_string(), child.into());
+        }
+
+        schema
+    }
+}
+
+fn item_schema(prop: &PropertyInfo, root: &mut RootSchema) -> SchemaObject {
+    let mut schema = match &prop.schema {
+        Some(x) => {
+            for (name, def) in &x.definitions {
+                root.definitions.entry(name.clone()).or_insert_with(|| def.clone());
+            }
+            x.schema.clone()
+        }
+        None => SchemaObject::default(),
+    };
+
+    let secret = prop.flags.contains(MetaFlag::SECRET);
+    if secret {
+        // Exported secrets are encrypted into strings.
+        let encrypted =
+            SchemaObject { instance_type: Some(InstanceType::String.into()), ..Default::default() };
+
+        let meta = schema.metadata.take();
+        schema = SchemaObject {
+            metadata: meta,
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![schema.into(), encrypted.into()]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+    }
+
+    let meta: &mut Metadata = schema.metadata();
+    let description = prop.description.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+    let description = description.trim();
+    if !description.is_empty() {
+        meta.description = Some(description.to_owned());
+    }
+
+    if !secret {
+        let default = prop.vtable.create_default();
+        meta.default = serde_json::to_value(default.as_serialize()).ok();
+    }
+
+    meta.deprecated |= prop.deprecated.is_some();
+    schema
+}
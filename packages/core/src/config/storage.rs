@@ -12,8 +12,13 @@
 //!   `import` and `exporter`.
 //! - **Monitoring**: Integrate external monitoring systems and receive updates about storage
 //!   modifications using the `replace_monitor`, `unset_monitor`, and `notify_editions` methods.
+//!   Item-level changes with their values are received with `subscribe_changes`.
+//! - **Layered Sources**: Stack configuration sources with explicit precedence using `set_layer`,
+//!   `clear_layer` and `source_layer`. Environment variables are mapped onto every item with
+//!   `set_env_prefix`.
+//! - **Snapshots**: Capture the whole storage with `snapshot`, and roll it back with `restore`.
 //! - **Encryption Support**: Securely encrypt data (when the encryption feature is enabled) using
-//!   `set_encryption_key`.
+//!   `set_crypt_key` or `set_crypt_key_provider`.
 
 use std::{
     any::{Any, TypeId},
@@ -26,8 +31,13 @@ use std::{
 use strseq::SharedStringSequence;
 
 use crate::{
-    config::{entity, noti},
-    shared::{archive, GroupId, ItemId, PathHash},
+    config::{
+        change,
+        entity::{self, Entity},
+        layer::{self, Layer},
+        migrate, noti,
+    },
+    shared::{archive, meta::MetaFlag, GroupId, ItemId, PathHash},
 };
 
 use super::{
@@ -88,6 +98,55 @@ pub trait Monitor: Send + Sync + 'static {
     }
 }
 
+/// Describes where an entity value update came from. Monitors can query it with
+/// [`current_update_origin`] from inside of [`Monitor::entity_value_updated`], which is always
+/// invoked synchronously on the thread that performed the update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum UpdateOrigin {
+    /// Local commit, e.g. [`group::Group::commit_elem`] or [`entity::EntityData::touch`].
+    #[default]
+    Commit,
+
+    /// Value was loaded from an archive via [`Storage::import`].
+    Import,
+
+    /// Value was edited by a monitor.
+    Monitor,
+
+    /// Value was rolled back by [`Storage::restore`].
+    Restore,
+
+    /// Value was read from an environment variable. See [`Storage::set_env_prefix`].
+    Environment,
+}
+
+thread_local! {
+    static UPDATE_ORIGIN: std::cell::Cell<UpdateOrigin> = Default::default();
+}
+
+/// Runs given closure while overriding the update origin of the current thread. Any value update
+/// performed inside of the closure will be reported as `origin` to the monitors.
+///
+/// The previous origin is restored on exit, even if the closure panics.
+pub fn with_update_origin<R>(origin: UpdateOrigin, f: impl FnOnce() -> R) -> R {
+    struct Restore(UpdateOrigin);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            UPDATE_ORIGIN.with(|x| x.set(self.0));
+        }
+    }
+
+    let _restore = Restore(UPDATE_ORIGIN.with(|x| x.replace(origin)));
+    f()
+}
+
+/// Gets the update origin of the current thread. See [`with_update_origin`].
+pub fn current_update_origin() -> UpdateOrigin {
+    UPDATE_ORIGIN.with(|x| x.get())
+}
+
 /* ---------------------------------------------------------------------------------------------- */
 /*                                           STORAGE API                                          */
 /* ---------------------------------------------------------------------------------------------- */
@@ -108,6 +167,41 @@ pub trait Monitor: Send + Sync + 'static {
 #[derive(Debug, Default, Clone)]
 pub struct Storage(Arc<inner::Inner>);
 
+/// Values of every live group, the layers and the archive cache, captured together at a single
+/// point by [`Storage::snapshot`]. Immutable, and cheap to clone.
+#[derive(Clone)]
+pub struct Snapshot(Arc<SnapshotData>);
+
+struct SnapshotData {
+    archive: archive::Archive,
+    layers: layer::LayerStack,
+    groups: std::collections::HashMap<PathHash, SnapshotGroup>,
+
+    #[cfg(feature = "crypt")]
+    crypt_salt: Option<Vec<u8>>,
+}
+
+struct SnapshotGroup {
+    template_type_id: TypeId,
+    values: Vec<entity::EntityValue>,
+}
+
+impl std::fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("archive", &self.0.archive)
+            .field("groups", &self.0.groups.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Snapshot {
+    /// Archive cache at the time of the snapshot. Values of live groups are not included.
+    pub fn archive(&self) -> &archive::Archive {
+        &self.0.archive
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum GroupFindError {
     #[error("Given path was not found")]
@@ -241,7 +335,11 @@ impl Storage {
         // This ID may not be used if group creation failed ... it's generally okay since we have
         // 2^63 trials.
         let register_id = GroupId::new_unique_incremental();
-        let entity_hook = Arc::new(EntityHookImpl { register_id, inner: Arc::downgrade(&self.0) });
+        let entity_hook = Arc::new(EntityHookImpl {
+            register_id,
+            path: path.clone(),
+            inner: Arc::downgrade(&self.0),
+        });
 
         debug_assert!(
             T::props__().windows(2).all(|x| x[0].index + 1 == x[1].index),
@@ -273,6 +371,8 @@ impl Storage {
             version: AtomicU64::new(1), // NOTE: This will trigger initial check_update() always.
             update_receiver_channel: tx_noti.receiver(true),
             path: path.clone(),
+            validate_candidate: T::validator__().map(|_| group::validate_candidate::<T> as _),
+            commit_lock: Default::default(),
         });
 
         self.0
@@ -280,13 +380,56 @@ impl Storage {
             .map(|context| group::Group::create_with__(context, unregister_anchor))
     }
 
+    /// Contexts of every live group.
+    pub(crate) fn group_contexts(&self) -> Vec<Arc<GroupContext>> {
+        self.0.group_contexts()
+    }
+
+    /// Gets the description of item `name` of the live group at `path`, which is taken from the
+    /// doc comment of the template field. Returns `None` if the group is not instantiated, or the
+    /// item has no description.
+    pub fn item_description<'a>(
+        &self,
+        path: impl IntoIterator<Item = &'a str>,
+        name: &str,
+    ) -> Option<&'static str> {
+        let group = self.0.find_group(&PathHash::new(path))?;
+        let item = group.sources.iter().find(|x| x.meta.name == name)?;
+        Some(item.meta.description).filter(|x| !x.is_empty())
+    }
+
+    /// JSON Schema of the archive files of this storage, describing every live group. See
+    /// [`super::schema`].
+    #[cfg(feature = "jsonschema")]
+    pub fn archive_schema(&self) -> crate::Schema {
+        super::schema::ArchiveSchema::from_storage(self).build()
+    }
+
     /// Create internal archive export task.
     ///
     /// You should explicitly call `confirm()` to retrieve the exported archive explcitly.
-    pub fn exporter(&self) -> inner::ExportTask {
+    pub fn exporter(&self) -> inner::ExportTask<'_> {
         inner::ExportTask::new(&self.0)
     }
 
+    /// Captures the values of every live group together with the layers and the archive cache.
+    /// Imports and commits are blocked while the values are copied, thus no group is captured in
+    /// the middle of a change.
+    pub fn snapshot(&self) -> Snapshot {
+        self.0.snapshot()
+    }
+
+    /// Rolls the storage back to given snapshot. Every live group whose values differ from the
+    /// snapshot receives them, and is notified once. The layers and the archive cache are replaced
+    /// with the ones of the snapshot.
+    ///
+    /// Groups created after the snapshot was taken are reset as if they were created right after
+    /// the snapshot: their items are restored to the template defaults, then to the values of the
+    /// snapshot's archive cache.
+    pub fn restore(&self, snapshot: &Snapshot) {
+        self.0.restore(snapshot)
+    }
+
     /// Deserializes the data.
     ///
     /// # Data Serialization Rules:
@@ -316,8 +459,113 @@ impl Storage {
     /// # Returns
     ///
     /// An instance of `ImportOnDrop` which will handle the import operation.
-    pub fn import(&self, archive: archive::Archive) -> inner::ImportOnDrop {
-        inner::ImportOnDrop::new(&self.0, archive)
+    ///
+    /// The archive is migrated to the current schema version first. See [`super::migrate`].
+    pub fn import(&self, mut archive: archive::Archive) -> inner::ImportOnDrop<'_> {
+        self.migrate(&mut archive);
+        inner::ImportOnDrop::new(&self.0, archive.into())
+    }
+
+    /// Imports a patch. Same as [`Storage::import`], except that the values and categories
+    /// removed by the patch reset the affected items to the value of the layers below, or to their
+    /// template defaults. Patches are not migrated; diff the migrated archives instead.
+    pub fn import_patch(&self, patch: archive::ArchivePatch) -> inner::ImportOnDrop<'_> {
+        inner::ImportOnDrop::new(&self.0, patch)
+    }
+
+    /// Replaces the content of given layer, and applies every item whose effective value has
+    /// changed to the live groups. See [`super::layer`] for the precedence rules.
+    ///
+    /// Groups created later observe the layer as well. Values of the persistent layers are also
+    /// written into the archive cache, while the ones of the transient layers are never exported.
+    /// See [`Layer::is_transient`].
+    pub fn set_layer(&self, layer: Layer, mut archive: archive::Archive) {
+        self.migrate(&mut archive);
+        self.0.replace_layer(layer, Some(archive))
+    }
+
+    /// Maps every item onto the environment variable named after its path, e.g.
+    /// `MYAPP__NET__SERVER__PORT` with prefix `MYAPP`. See [`super::env`].
+    ///
+    /// Variables of the live groups are read immediately, and those of groups created later are
+    /// read on their creation. The values form the [`Layer::Environment`] layer, thus any content
+    /// previously set to it is replaced.
+    pub fn set_env_prefix(&self, prefix: impl Into<String>) {
+        *self.0.env_prefix.write() = Some(prefix.into());
+        self.reload_env();
+    }
+
+    /// Creates a receiver of item-level change events, which carry the previous and new value of
+    /// each changed item. See [`super::change`].
+    pub fn subscribe_changes(&self) -> super::change::ChangeReceiver {
+        self.0.changes.subscribe()
+    }
+
+    /// Reads the environment variables of every live group again, and applies the changed ones.
+    /// Does nothing unless a prefix was set with [`Storage::set_env_prefix`].
+    pub fn reload_env(&self) {
+        self.0.reload_env()
+    }
+
+    /// Registers a migration, which upgrades archives of the previous schema version to `version`.
+    /// The schema version of the storage is the highest version among the registered migrations.
+    pub fn add_migration(
+        &self,
+        version: u32,
+        migrate: impl Fn(&mut archive::Archive) + Send + Sync + 'static,
+    ) {
+        self.0.migrations.write().add(version, Box::new(migrate));
+    }
+
+    /// Registers a migration which transforms the category at `path` only. It's not invoked if
+    /// the archive has no such category.
+    pub fn add_group_migration<'a>(
+        &self,
+        version: u32,
+        path: impl IntoIterator<Item = &'a str>,
+        migrate: impl Fn(&mut archive::Archive) + Send + Sync + 'static,
+    ) {
+        let path: Vec<String> = path.into_iter().map(ToOwned::to_owned).collect();
+        self.add_migration(version, move |archive| {
+            if let Some(node) = archive.find_path_mut(&path) {
+                migrate(node);
+            }
+        });
+    }
+
+    /// Current schema version, which is written into exported archives.
+    pub fn schema_version(&self) -> u32 {
+        self.0.migrations.read().current()
+    }
+
+    /// Upgrades the archive to the current schema version, by applying every registered migration
+    /// of higher version than the archive's.
+    pub fn migrate(&self, archive: &mut archive::Archive) {
+        self.0.migrations.read().upgrade(archive)
+    }
+
+    /// Removes given layer. Items which were supplied by it are restored to the value of the layer
+    /// below, or to the template default if no other layer defines them.
+    pub fn clear_layer(&self, layer: Layer) {
+        self.0.replace_layer(layer, None)
+    }
+
+    /// Returns a copy of the archive of given layer, if it's set.
+    pub fn layer(&self, layer: Layer) -> Option<archive::Archive> {
+        self.0.layers.read().get(layer).cloned()
+    }
+
+    /// Reports which layer supplies the value of item `name` of the group at `path`. Returns
+    /// [`Layer::Defaults`] if no layer defines it, which means the template default is in use.
+    ///
+    /// Values applied through [`Storage::import`] or local commits are not part of any layer, thus
+    /// are not reflected here.
+    pub fn source_layer<'a>(
+        &self,
+        path: impl IntoIterator<Item = &'a str> + Clone,
+        name: &str,
+    ) -> Layer {
+        self.0.layers.read().source_of(path, name).unwrap_or(Layer::Defaults)
     }
 
     /// Replaces the current monitor with the provided one.
@@ -354,9 +602,33 @@ impl Storage {
     /// * `key` - Byte slice representing the encryption key.
     #[cfg(feature = "crypt")]
     pub fn set_crypt_key(&self, key: impl AsRef<[u8]>) {
-        use sha2::{Digest, Sha256};
-        let arr = &*Sha256::new().chain_update(key).finalize();
-        self.0.crypt_key.write().replace(std::array::from_fn(|index| arr[index]));
+        self.set_crypt_key_provider(super::crypt::StaticKey::from_hashed(key))
+    }
+
+    /// Sets the provider of the encryption key, which is queried whenever a secret value is
+    /// encrypted or decrypted. See [`super::crypt`] for available providers.
+    #[cfg(feature = "crypt")]
+    pub fn set_crypt_key_provider(&self, provider: impl super::crypt::KeyProvider) {
+        self.0.crypt_key.write().replace(Arc::new(provider));
+    }
+
+    /// Re-encrypts every secret value with the key of `new`, then installs `new` as the key
+    /// provider.
+    ///
+    /// Encrypted values of the archive cache and of every layer, including the ones belonging to
+    /// groups which are not instantiated yet, are decrypted with the key of `old`. Values of live
+    /// groups are dumped into the archive cache with the new key. A fresh salt is generated if
+    /// `new` requires one.
+    ///
+    /// Values which can't be decrypted with the old key are left untouched, and listed in the
+    /// report. Fails only if either key can't be loaded, in which case nothing is modified.
+    #[cfg(feature = "crypt")]
+    pub fn rotate_crypt_key(
+        &self,
+        old: impl super::crypt::KeyProvider,
+        new: impl super::crypt::KeyProvider,
+    ) -> Result<super::crypt::KeyRotationReport, super::crypt::CryptKeyError> {
+        self.0.rotate_crypt_key(&old, Arc::new(new))
     }
 }
 
@@ -381,6 +653,7 @@ impl Drop for GroupUnregisterHook {
 
 struct EntityHookImpl {
     register_id: GroupId,
+    path: SharedStringSequence,
     inner: Weak<inner::Inner>,
 }
 
@@ -391,25 +664,74 @@ impl EntityEventHook for EntityHookImpl {
         let Some(inner) = self.inner.upgrade() else { return };
         inner.on_value_update(self.register_id, data, silent);
     }
+
+    fn on_value_applied(
+        &self,
+        data: &entity::EntityData,
+        old: &entity::EntityValue,
+        new: &entity::EntityValue,
+        version: u64,
+    ) {
+        let Some(inner) = self.inner.upgrade() else { return };
+        let json = |value: &entity::EntityValue| {
+            if data.meta.flags.contains(MetaFlag::SECRET) {
+                return serde_json::Value::Null;
+            }
+
+            serde_json::to_value(value.as_serialize()).unwrap_or_default()
+        };
+
+        inner.changes.emit(|| change::ChangeEvent {
+            group_id: self.register_id,
+            path: self.path.clone(),
+            item_id: data.id,
+            name: data.meta.name,
+            old: json(old),
+            new: json(new),
+            origin: current_update_origin(),
+            version,
+        });
+    }
 }
 
 mod inner {
-    use std::{collections::HashMap, mem::ManuallyDrop};
+    use std::collections::HashMap;
 
     use derive_setters::Setters;
     use parking_lot::RwLock;
 
+    #[cfg(feature = "crypt")]
+    use crate::config::crypt;
     use crate::{
-        config::entity::Entity,
-        shared::{archive::Archive, meta::MetaFlag, StorageId},
+        config::{
+            entity::{EntityUpdateError, Validation},
+            env,
+            import::{ImportReport, ItemOutcome, ItemReport, UnknownKey},
+            layer,
+        },
+        shared::{
+            archive::{Archive, ArchivePatch},
+            StorageId,
+        },
     };
 
     use super::*;
 
+    /// Root values which carry metadata of the archive, rather than item values. The salt key is
+    /// written literally, as it's reserved even if the `crypt` feature is disabled.
+    const RESERVED_ROOT_KEYS: [&str; 2] = [migrate::SCHEMA_VERSION_KEY, "$crypt-salt"];
+
     /// Manages and drives internal storage events.
     ///
     /// Primarily responsible for handling update requests and orchestrating
     /// the underlying storage mechanisms.
+    ///
+    /// # Lock order
+    ///
+    /// `layers`, then `archive`, then the `commit_lock` of each group in ascending order of group
+    /// ID, then the values of its items. `path_hashes`, `all_groups` and `monitors` are only held
+    /// to look up or notify the live groups, and no commit lock is acquired while holding them;
+    /// see [`Inner::live_groups`].
     #[derive(cs::Debug)]
     pub(super) struct Inner {
         /// Unique(during runtime) identifier for this storage.
@@ -439,18 +761,39 @@ mod inner {
         /// currently non-existent.
         pub archive: RwLock<archive::Archive>,
 
-        /// AES-256 encryption key for securing data.
+        /// Archives of each configuration source layer. Their merged content is always reflected
+        /// to `archive`.
+        pub layers: RwLock<layer::LayerStack>,
+
+        /// Schema migrations of imported archives.
+        #[debug(skip)]
+        pub migrations: RwLock<migrate::Migrations>,
+
+        /// Prefix of the environment variables mapped onto every item, if enabled.
+        pub env_prefix: RwLock<Option<String>>,
+
+        /// Receivers of item-level change events.
+        #[debug(skip)]
+        pub changes: change::ChangeChannel,
+
+        /// Provider of AES-256 encryption key for securing data.
         ///
         /// This key is used when the encryption feature is enabled. It ensures that stored data is
         /// encrypted, adding an additional layer of security.
         #[cfg(feature = "crypt")]
         #[debug(with = "fmt_encryption_key")]
-        pub crypt_key: RwLock<Option<[u8; 32]>>,
+        pub crypt_key: RwLock<Option<Arc<dyn crypt::KeyProvider>>>,
+
+        /// Salt for the key providers which derive the key. Generated on first use, and written to
+        /// every exported archive.
+        #[cfg(feature = "crypt")]
+        #[debug(skip)]
+        pub crypt_salt: RwLock<Option<Vec<u8>>>,
     }
 
     #[cfg(feature = "crypt")]
     fn fmt_encryption_key(
-        key: &RwLock<Option<[u8; 32]>>,
+        key: &RwLock<Option<Arc<dyn crypt::KeyProvider>>>,
         f: &mut std::fmt::Formatter<'_>,
     ) -> std::fmt::Result {
         let exists = key.read().is_some();
@@ -475,8 +818,14 @@ mod inner {
                 id: StorageId::new_unique_incremental(),
                 monitors: Default::default(),
                 archive: Default::default(),
+                layers: Default::default(),
+                migrations: Default::default(),
+                env_prefix: Default::default(),
+                changes: Default::default(),
                 #[cfg(feature = "crypt")]
                 crypt_key: Default::default(),
+                #[cfg(feature = "crypt")]
+                crypt_salt: Default::default(),
 
                 // NOTE: Uses 4 shards for both maps. The default implementation's shared amount,
                 all_groups: Default::default(),
@@ -491,6 +840,22 @@ mod inner {
             }
         }
 
+        pub fn group_contexts(&self) -> Vec<Arc<GroupContext>> {
+            self.all_groups.read().values().map(|x| x.context.clone()).collect()
+        }
+
+        /// Collects the live groups in ascending order of group ID, which is the order their
+        /// commit locks are acquired in. `all_groups` is released on return, thus a group holding
+        /// its commit lock may look it up meanwhile.
+        fn live_groups(&self) -> Vec<(Arc<GroupContext>, noti::Sender)> {
+            let mut groups: Vec<_> = (self.all_groups.read().values())
+                .map(|x| (x.context.clone(), x.evt_on_update.clone()))
+                .collect();
+
+            groups.sort_by_key(|x| x.0.group_id);
+            groups
+        }
+
         pub fn find_group(&self, path_hash: &PathHash) -> Option<Arc<GroupContext>> {
             self.path_hashes
                 .read()
@@ -566,14 +931,23 @@ mod inner {
             let inserted = context.clone();
             let rg = GroupRegistration { context, evt_on_update };
 
-            // If the path already exists in the archive, load the corresponding node.
-            if let Some(node) = self.archive.read().find_path(rg.context.path.iter()) {
+            self.overlay_env(&rg.context);
+
+            // If the path already exists in the archive or in any transient layer, load the
+            // corresponding node.
+            let node = {
+                let layers = self.layers.read();
+                Self::initial_node(&layers, &self.archive.read(), &rg.context.path)
+            };
+
+            if let Some(node) = node {
                 Self::load_node(
                     &rg.context,
-                    node,
+                    &node,
+                    |_, _| {},
                     |_, _| {},
                     #[cfg(feature = "crypt")]
-                    Self::crypt_key_loader(&self.crypt_key),
+                    self.crypt_key_loader(),
                 );
             }
 
@@ -599,7 +973,7 @@ mod inner {
         pub fn unregister_group(&self, group_id: GroupId, path_hash: PathHash) {
             {
                 let mut path_hashes = self.path_hashes.write();
-                if !path_hashes.get(&path_hash).is_some_and(|v| *v == group_id) {
+                if path_hashes.get(&path_hash) != Some(&group_id) {
                     tr::debug!(?group_id, ?path_hash, "unregister_group() call to unexist group");
                     return;
                 };
@@ -607,7 +981,8 @@ mod inner {
                 path_hashes.remove(&path_hash);
             }
 
-            if let Some(ctx) = self.all_groups.write().remove(&group_id) {
+            let removed = self.all_groups.write().remove(&group_id);
+            if let Some(ctx) = removed {
                 let _s = tr::info_span!(
                     "unregister_group()",
                     template = ?ctx.context.template_name,
@@ -621,12 +996,20 @@ mod inner {
                 // `create_impl` function.
 
                 // For valid removals, add contents to the cached archive.
-                Self::dump_node(
-                    &ctx.context,
-                    &mut self.archive.write(),
-                    #[cfg(feature = "crypt")]
-                    Self::crypt_key_loader(&self.crypt_key),
-                );
+                {
+                    let layers = self.layers.read();
+                    let mut cache = self.archive.write();
+                    let base = cache.find_path(ctx.context.path.iter()).cloned();
+
+                    Self::dump_node(
+                        &ctx.context,
+                        &mut cache,
+                        #[cfg(feature = "crypt")]
+                        self.crypt_key_loader(),
+                    );
+
+                    Self::unpin_transient(&layers, &ctx.context, &mut cache, base.as_ref());
+                }
 
                 // Notify about the removal
                 self._write_event_retained(|m| m.group_removed(group_id));
@@ -698,23 +1081,614 @@ mod inner {
             });
         }
 
+        pub fn replace_layer(&self, layer: Layer, archive: Option<Archive>) {
+            #[cfg(feature = "crypt")]
+            let archive = archive.map(|mut archive| {
+                self.adopt_crypt_salt(&mut archive);
+                archive
+            });
+
+            let origin = match layer {
+                Layer::Environment => UpdateOrigin::Environment,
+                _ => UpdateOrigin::Import,
+            };
+
+            self.edit_layers(origin, |layers| {
+                layers.replace(layer, archive);
+            })
+        }
+
+        /// Collects the environment variables of every live group into the environment layer.
+        pub fn reload_env(&self) {
+            let Some(prefix) = self.env_prefix.read().clone() else { return };
+
+            let mut archive = Archive::default();
+            for ctx in self.group_contexts() {
+                env::collect(&prefix, &ctx, &mut archive);
+            }
+
+            self.replace_layer(Layer::Environment, Some(archive))
+        }
+
+        /// Merges the environment variables of a group, which is about to be registered, into
+        /// the environment layer.
+        fn overlay_env(&self, ctx: &GroupContext) {
+            let Some(prefix) = self.env_prefix.read().clone() else { return };
+
+            let mut archive = Archive::default();
+            env::collect(&prefix, ctx, &mut archive);
+
+            if !archive.is_empty() {
+                self.edit_layers(UpdateOrigin::Environment, |layers| {
+                    layers.merge(Layer::Environment, archive)
+                });
+            }
+        }
+
+        /// Applies every item whose effective value was changed by the edit. Only the changes of
+        /// the persistent layers are written into the archive cache.
+        fn edit_layers(&self, origin: UpdateOrigin, edit: impl FnOnce(&mut layer::LayerStack)) {
+            let mut layers = self.layers.write();
+            let mut cache = self.archive.write();
+            let prev = layers.view(&cache);
+            let prev_persistent = layers.persistent();
+            edit(&mut layers);
+
+            cache.apply_patch(prev_persistent.diff(&layers.persistent()));
+            let patch = prev.diff(&layers.view(&cache));
+            if !patch.is_empty() {
+                self.apply_patch_to_groups(&patch, &prev, origin, false, &mut Default::default());
+            }
+        }
+
+        /// Node of the cache at `path`, overlaid with the transient layers. Groups load this on
+        /// creation.
+        fn initial_node(
+            layers: &layer::LayerStack,
+            cache: &Archive,
+            path: &SharedStringSequence,
+        ) -> Option<Archive> {
+            let transient = layers.transient_at(path.iter());
+            match cache.find_path(path.iter()) {
+                Some(node) => Some(node.clone().merge(transient)),
+                None => Some(transient).filter(|x| !x.is_empty()),
+            }
+        }
+
+        /// Replaces the dumped values of the group, which are supplied by transient layers, with
+        /// the ones of `base`. Values missing from `base` are left out, thus transient values are
+        /// never persisted.
+        fn unpin_transient(
+            layers: &layer::LayerStack,
+            ctx: &GroupContext,
+            dump: &mut Archive,
+            base: Option<&Archive>,
+        ) {
+            let transient = layers.transient_at(ctx.path.iter());
+            let Some(node) = dump.find_path_mut(ctx.path.iter()) else { return };
+
+            for (name, _) in transient.iter_values() {
+                if node.get_value(name).is_none() {
+                    continue;
+                }
+
+                match base.and_then(|x| x.get_value(name)) {
+                    Some(value) => node.insert_value(name, value.clone()),
+                    None => drop(node.remove_value(name)),
+                }
+            }
+        }
+
+        /// Removes legacy keys of live group items from the archive, as they're superseded by the
+        /// current names on export.
+        ///
+        /// Categories without a live group are left as they are: the storage doesn't know their
+        /// template until a group is created on them, which still reads the legacy keys.
+        fn remove_previous_names(&self, archive: &mut Archive) {
+            for group in self.all_groups.read().values() {
+                let ctx = &group.context;
+                let Some(node) = archive.find_path_mut(ctx.path.iter()) else { continue };
+
+                for key in ctx.sources.iter().flat_map(|x| x.meta.previous_names) {
+                    node.remove_value(key);
+                }
+            }
+        }
+
+        /// Lists every value of the archive which matches no item of the live groups, including
+        /// the ones under categories without any live group. Reserved root values are ignored.
+        fn find_unknown_keys(&self, archive: &Archive, report: &mut ImportReport) {
+            for (name, _) in archive.iter_values() {
+                if !RESERVED_ROOT_KEYS.contains(&name) {
+                    report.unknown.push(UnknownKey { path: Vec::new(), name: name.to_owned() });
+                }
+            }
+
+            for (key, child) in archive.iter_paths() {
+                self.find_unknown_keys_at(child, &mut vec![key.to_owned()], report);
+            }
+        }
+
+        fn find_unknown_keys_at(
+            &self,
+            node: &Archive,
+            path: &mut Vec<String>,
+            report: &mut ImportReport,
+        ) {
+            let group = self.find_group(&PathHash::new(path.iter().map(String::as_str)));
+
+            for (name, _) in node.iter_values() {
+                let known = group.as_ref().is_some_and(|ctx| {
+                    ctx.sources.iter().any(|x| x.meta.archive_keys().any(|key| key == name))
+                });
+
+                if !known {
+                    report.unknown.push(UnknownKey { path: path.clone(), name: name.to_owned() });
+                }
+            }
+
+            for (key, child) in node.iter_paths() {
+                path.push(key.to_owned());
+                self.find_unknown_keys_at(child, path, report);
+                path.pop();
+            }
+        }
+
+        pub fn snapshot(&self) -> Snapshot {
+            let layers = self.layers.read();
+            let archive = self.archive.read();
+            let live = self.live_groups();
+
+            // Holding every commit lock at once makes the values consistent across groups.
+            let _guards: Vec<_> = live.iter().map(|x| x.0.commit_lock.read()).collect();
+
+            let groups = live
+                .iter()
+                .map(|(ctx, _)| {
+                    let values = ctx.sources.iter().map(|x| x.property_value().1).collect();
+                    let saved = SnapshotGroup { template_type_id: ctx.template_type_id, values };
+                    (PathHash::new(ctx.path.iter()), saved)
+                })
+                .collect();
+
+            Snapshot(Arc::new(SnapshotData {
+                archive: archive.clone(),
+                layers: layers.clone(),
+                groups,
+                #[cfg(feature = "crypt")]
+                crypt_salt: self.crypt_salt.read().clone(),
+            }))
+        }
+
+        pub fn restore(&self, snapshot: &Snapshot) {
+            let data = &*snapshot.0;
+            let mut layers = self.layers.write();
+            let mut cache = self.archive.write();
+            *layers = data.layers.clone();
+
+            #[cfg(feature = "crypt")]
+            {
+                *self.crypt_salt.write() = data.crypt_salt.clone();
+            }
+
+            #[cfg(feature = "crypt")]
+            let key_loader = self.crypt_key_loader();
+
+            with_update_origin(UpdateOrigin::Restore, || {
+                for (ctx, evt_on_update) in self.live_groups() {
+                    let mut updates = Vec::new();
+                    let saved = data
+                        .groups
+                        .get(&PathHash::new(ctx.path.iter()))
+                        .filter(|x| x.template_type_id == ctx.template_type_id);
+
+                    let commit_guard = ctx.commit_lock.write();
+                    let has_update = if let Some(saved) = saved {
+                        Self::restore_node(&ctx, &saved.values, |g_id, e_id| {
+                            updates.push((g_id, e_id))
+                        })
+                    } else {
+                        let names: Vec<_> = ctx.sources.iter().map(|x| x.meta.name).collect();
+                        let mut staged = StagedNode::default();
+                        Self::stage_reset(&ctx, &names, &mut staged, |_, _| {});
+
+                        if let Some(node) = Self::initial_node(&layers, &data.archive, &ctx.path) {
+                            Self::stage_node(
+                                &ctx,
+                                &node,
+                                &mut staged,
+                                |_, _| {},
+                                #[cfg(feature = "crypt")]
+                                key_loader,
+                            );
+                        }
+
+                        Self::commit_node(
+                            &ctx,
+                            staged,
+                            |g_id, e_id| updates.push((g_id, e_id)),
+                            |_, _| {},
+                        )
+                    };
+                    drop(commit_guard);
+
+                    if has_update {
+                        for (g_id, e_id) in updates {
+                            self._write_event_retained(|m| m.entity_value_updated(g_id, e_id));
+                        }
+
+                        evt_on_update.notify();
+                    }
+                }
+            });
+
+            *cache = data.archive.clone();
+        }
+
+        /// Applies the patch onto every live group. Items removed by the patch are reset to their
+        /// template defaults; `base` is the archive the patch was created from.
+        ///
+        /// Every group is staged and checked with its template validator first, and their commit
+        /// locks are held until all of them are applied. If `strict` is set, nothing is applied
+        /// unless `report` is clean afterwards, and false is returned.
+        fn apply_patch_to_groups(
+            &self,
+            patch: &ArchivePatch,
+            base: &Archive,
+            origin: UpdateOrigin,
+            strict: bool,
+            report: &mut ImportReport,
+        ) -> bool {
+            let upserts = patch.upserts();
+
+            #[cfg(feature = "crypt")]
+            let key_loader = self.crypt_key_loader();
+
+            let groups = self.live_groups();
+            let guards: Vec<_> = groups.iter().map(|x| x.0.commit_lock.write()).collect();
+            let mut checked = Vec::with_capacity(groups.len());
+
+            for (ctx, _) in &groups {
+                let path = || ctx.path.iter();
+                let mut report_item = |name, outcome| {
+                    let path = path().map(ToOwned::to_owned).collect();
+                    report.items.push(ItemReport { path, name, outcome });
+                };
+
+                // Removals and upserts are applied as a single change of the group.
+                let removed = patch.removed_values_at(base, path());
+                let mut staged = StagedNode::default();
+                Self::stage_reset(ctx, &removed, &mut staged, &mut report_item);
+
+                if let Some(node) = upserts.find_path(path()) {
+                    Self::stage_node(
+                        ctx,
+                        node,
+                        &mut staged,
+                        &mut report_item,
+                        #[cfg(feature = "crypt")]
+                        key_loader,
+                    );
+                }
+
+                checked.push(Self::check_node(ctx, staged, &mut report_item));
+            }
+
+            if strict && !report.is_clean() {
+                return false;
+            }
+
+            with_update_origin(origin, || {
+                let mut updates = Vec::new();
+                let mut updated_groups = Vec::new();
+
+                for ((ctx, evt_on_update), changes) in groups.iter().zip(checked) {
+                    let changes = changes.unwrap_or_default();
+                    if Self::apply_node(ctx, changes, |g_id, e_id| updates.push((g_id, e_id))) {
+                        updated_groups.push(evt_on_update);
+                    }
+                }
+
+                drop(guards);
+
+                for (g_id, e_id) in updates {
+                    self._write_event_retained(|m| m.entity_value_updated(g_id, e_id));
+                }
+
+                for evt_on_update in updated_groups {
+                    evt_on_update.notify();
+                }
+            });
+
+            true
+        }
+
         /// ⚠️ **CAUTION!** Do NOT alter this literal! Any modification will DESTROY all existing
         /// encrypted data irreparably! ⚠️
         ///
         /// PREFIX itself is valid base64, which is decorated word 'secret'.
+        ///
+        /// Legacy format, which is encrypted with [`Self::LEGACY_CRYPT_NONCE`]. Only read.
         #[cfg(feature = "crypt")]
         const CRYPT_PREFIX: &'static str = "+/+sE/cRE+t//";
 
+        /// ⚠️ **CAUTION!** Do NOT alter this literal! ⚠️
+        ///
+        /// Versioned successor of [`Self::CRYPT_PREFIX`]. The payload is the random nonce followed
+        /// by the ciphertext.
+        #[cfg(feature = "crypt")]
+        const CRYPT_PREFIX_V2: &'static str = "+/+sE/cRE+t/2/";
+
         #[cfg(feature = "crypt")]
-        fn crypt_key_loader(
-            key: &RwLock<Option<[u8; 32]>>,
-        ) -> impl Fn() -> Option<[u8; 32]> + '_ + Copy {
-            || key.read().or_else(Self::crypt_sys_key)
+        fn crypt_key_loader(&self) -> impl Fn() -> Option<[u8; 32]> + '_ + Copy {
+            move || {
+                let Some(provider) = self.crypt_key.read().clone() else {
+                    return Self::crypt_sys_key();
+                };
+
+                let salt = provider.requires_salt().then(|| {
+                    self.crypt_salt.write().get_or_insert_with(crypt::generate_salt).clone()
+                });
+
+                provider
+                    .load_key(salt.as_deref())
+                    .map_err(|error| tr::warn!(%error, "Failed to load crypt key"))
+                    .ok()
+            }
         }
 
-        /// Uses hard coded NONCE, just to run the algorithm.
         #[cfg(feature = "crypt")]
-        const CRYPT_NONCE: [u8; 12] = [15, 43, 5, 12, 6, 66, 126, 231, 141, 18, 33, 71];
+        pub fn rotate_crypt_key(
+            &self,
+            old: &dyn crypt::KeyProvider,
+            new: Arc<dyn crypt::KeyProvider>,
+        ) -> Result<crypt::KeyRotationReport, crypt::CryptKeyError> {
+            let mut layers = self.layers.write();
+            let mut cache = self.archive.write();
+
+            let old_key = {
+                let mut salt = self.crypt_salt.write();
+                let salt =
+                    old.requires_salt().then(|| salt.get_or_insert_with(crypt::generate_salt));
+                old.load_key(salt.map(|x| &x[..]))?
+            };
+
+            let new_salt = new.requires_salt().then(crypt::generate_salt);
+            let new_key = new.load_key(new_salt.as_deref())?;
+
+            let mut report = crypt::KeyRotationReport::default();
+            let mut rotate = |layer, archive: &mut Archive| {
+                Self::rotate_node(
+                    archive,
+                    &old_key,
+                    &new_key,
+                    &mut Vec::new(),
+                    &mut |path, name| {
+                        report.failed.push(crypt::RotationFailure {
+                            layer,
+                            path: path.iter().map(|x| x.to_string()).collect(),
+                            name: name.into(),
+                        })
+                    },
+                )
+            };
+
+            let mut rotated = rotate(None, &mut cache);
+            for (layer, archive) in layers.iter_mut() {
+                rotated += rotate(Some(layer), archive);
+            }
+
+            report.rotated = rotated;
+            if let Some(salt) = new_salt {
+                *self.crypt_salt.write() = Some(salt);
+            }
+
+            *self.crypt_key.write() = Some(new);
+
+            // Stale salts are replaced, so that they're not adopted again on re-import.
+            for archive in std::iter::once(&mut *cache).chain(layers.iter_mut().map(|(_, x)| x)) {
+                if archive.get_value(crypt::CRYPT_SALT_KEY).is_some() {
+                    self.write_crypt_salt(archive);
+                }
+            }
+
+            // Live values are re-encrypted with the new key. Cached copies of them are now up to
+            // date, regardless of whether they could be rotated.
+            let mut live = Archive::default();
+            for group in self.all_groups.read().values() {
+                let ctx = &group.context;
+                Self::dump_node(ctx, &mut live, self.crypt_key_loader());
+                Self::unpin_transient(&layers, ctx, &mut live, cache.find_path(ctx.path.iter()));
+            }
+
+            report.failed.retain(|x| {
+                x.layer.is_some()
+                    || live.find_path(&x.path).and_then(|n| n.get_value(&x.name)).is_none()
+            });
+
+            cache.merge_from(live);
+            Ok(report)
+        }
+
+        /// Re-encrypts every encrypted value of given archive node recursively. Returns number of
+        /// rotated values.
+        #[cfg(feature = "crypt")]
+        fn rotate_node(
+            node: &mut Archive,
+            old_key: &[u8; 32],
+            new_key: &[u8; 32],
+            path: &mut Vec<compact_str::CompactString>,
+            on_failure: &mut impl FnMut(&[compact_str::CompactString], &str),
+        ) -> usize {
+            use aes_gcm::aead::{Aead, KeyInit};
+
+            let mut rotated = 0;
+            let cipher = aes_gcm::Aes256Gcm::new(old_key.into());
+
+            for (name, value) in node.iter_values_mut() {
+                let Some((nonce, bin)) = value.as_str().and_then(Self::crypt_decode) else {
+                    continue;
+                };
+
+                let Some(enc) = cipher
+                    .decrypt(&nonce.into(), &bin[..])
+                    .ok()
+                    .and_then(|plain| Self::crypt_encode(new_key, &plain))
+                else {
+                    on_failure(path, name);
+                    continue;
+                };
+
+                *value = enc.into();
+                rotated += 1;
+            }
+
+            for (key, child) in node.iter_paths_mut() {
+                path.push(key.into());
+                rotated += Self::rotate_node(child, old_key, new_key, path, on_failure);
+                path.pop();
+            }
+
+            rotated
+        }
+
+        /// Takes the key derivation salt from the root of given archive, unless the storage
+        /// already has one. Returns true if the archive was rewritten.
+        ///
+        /// Replacing the salt would make every secret which was already cached or dumped under the
+        /// current one undecryptable. Therefore, if the archive carries a different salt, its
+        /// encrypted values are re-encrypted with the key derived from the current salt instead.
+        #[cfg(feature = "crypt")]
+        fn adopt_crypt_salt(&self, archive: &mut Archive) -> bool {
+            use base64::prelude::*;
+
+            let Some(str) = archive.get_value(crypt::CRYPT_SALT_KEY).and_then(|x| x.as_str())
+            else {
+                return false;
+            };
+
+            let salt = match BASE64_STANDARD_NO_PAD.decode(str) {
+                Ok(salt) => salt,
+                Err(error) => {
+                    tr::warn!(%error, "Invalid crypt salt in archive");
+                    return false;
+                }
+            };
+
+            let current = {
+                let mut current = self.crypt_salt.write();
+                match &*current {
+                    None => {
+                        *current = Some(salt);
+                        return false;
+                    }
+                    Some(x) if *x == salt => return false,
+                    Some(x) => x.clone(),
+                }
+            };
+
+            // Salt doesn't matter unless the key is derived from it.
+            let Some(provider) = self.crypt_key.read().clone().filter(|x| x.requires_salt()) else {
+                return false;
+            };
+
+            let keys = provider
+                .load_key(Some(&salt))
+                .and_then(|old| Ok((old, provider.load_key(Some(&current))?)));
+
+            let (old_key, new_key) = match keys {
+                Ok(keys) => keys,
+                Err(error) => {
+                    tr::warn!(%error, "Failed to load crypt key of foreign salt");
+                    return false;
+                }
+            };
+
+            let mut failed = 0;
+            Self::rotate_node(archive, &old_key, &new_key, &mut Vec::new(), &mut |_, _| {
+                failed += 1
+            });
+
+            if failed > 0 {
+                tr::warn!(failed, "Values under foreign crypt salt couldn't be re-encrypted");
+            }
+
+            self.write_crypt_salt(archive);
+            true
+        }
+
+        /// Writes the key derivation salt into the root of given archive, if it exists.
+        #[cfg(feature = "crypt")]
+        fn write_crypt_salt(&self, archive: &mut Archive) {
+            use base64::prelude::*;
+
+            if let Some(salt) = self.crypt_salt.read().as_ref() {
+                let salt = BASE64_STANDARD_NO_PAD.encode(salt);
+                archive.insert_value(crypt::CRYPT_SALT_KEY, salt.into());
+            }
+        }
+
+        /// Hard coded NONCE of the legacy format. Never use this for encryption, as reusing nonce
+        /// under the same key breaks AES-GCM.
+        #[cfg(feature = "crypt")]
+        const LEGACY_CRYPT_NONCE: [u8; 12] = [15, 43, 5, 12, 6, 66, 126, 231, 141, 18, 33, 71];
+
+        /// Encrypts given plain data into the string representation, with a random nonce.
+        #[cfg(feature = "crypt")]
+        fn crypt_encode(key: &[u8; 32], plain: &[u8]) -> Option<String> {
+            use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+            use base64::prelude::*;
+
+            let cipher = aes_gcm::Aes256Gcm::new(key.into());
+            let nonce = aes_gcm::Aes256Gcm::generate_nonce(&mut OsRng);
+            let enc = cipher.encrypt(&nonce, plain).map_err(|error| {
+                tr::warn!(%error, "Encryption failed");
+            });
+
+            let mut payload = nonce.to_vec();
+            payload.extend(enc.ok()?);
+
+            Some(format!("{}{}", Self::CRYPT_PREFIX_V2, BASE64_STANDARD_NO_PAD.encode(payload)))
+        }
+
+        /// Splits the string representation of an encrypted value into its nonce and ciphertext.
+        /// Returns `None` if it's not an encrypted value.
+        #[cfg(feature = "crypt")]
+        fn crypt_decode(str: &str) -> Option<([u8; 12], Vec<u8>)> {
+            use base64::prelude::*;
+
+            let (body, legacy) = if let Some(body) = str.strip_prefix(Self::CRYPT_PREFIX_V2) {
+                (body, false)
+            } else if let Some(body) = str.strip_prefix(Self::CRYPT_PREFIX) {
+                (body, true)
+            } else {
+                tr::debug!("Non-encrypted string repr. serve as-is.");
+                return None;
+            };
+
+            let bin = BASE64_STANDARD_NO_PAD
+                .decode(body)
+                .map_err(|error| {
+                    tr::debug!(
+                        %error,
+                        "Crypt-prefixed string is not valid base64. \
+                         Trying to parse as plain string."
+                    )
+                })
+                .ok()?;
+
+            if legacy {
+                return Some((Self::LEGACY_CRYPT_NONCE, bin));
+            }
+
+            if bin.len() < 12 {
+                tr::debug!("Crypt-prefixed payload is too short. Trying to parse as plain string.");
+                return None;
+            }
+
+            let (nonce, data) = bin.split_at(12);
+            Some((nonce.try_into().unwrap(), data.to_vec()))
+        }
 
         #[cfg(feature = "crypt")]
         fn crypt_sys_key() -> Option<[u8; 32]> {
@@ -765,9 +1739,6 @@ mod inner {
 
                 #[cfg(feature = "crypt")]
                 'encryption: {
-                    use aes_gcm::aead::{Aead, KeyInit};
-                    use base64::prelude::*;
-
                     if !meta.metadata.flags.contains(MetaFlag::SECRET) {
                         break 'encryption;
                     }
@@ -787,17 +1758,8 @@ mod inner {
                         continue '_outer;
                     };
 
-                    let cipher = aes_gcm::Aes256Gcm::new(key.into());
-                    let Ok(enc) = cipher.encrypt(&Self::CRYPT_NONCE.into(), &json[..]) else {
-                        tr::warn!("Encryption failed");
-                        continue '_outer;
-                    };
-
-                    *dst = serde_json::Value::String(format!(
-                        "{}{}",
-                        Self::CRYPT_PREFIX,
-                        BASE64_STANDARD_NO_PAD.encode(&enc)
-                    ));
+                    let Some(enc) = Self::crypt_encode(key, &json) else { continue '_outer };
+                    *dst = serde_json::Value::String(enc);
 
                     continue '_outer;
                 }
@@ -820,30 +1782,64 @@ mod inner {
         fn load_node(
             ctx: &GroupContext,
             node: &archive::Archive,
-            mut notify_update: impl FnMut(GroupId, ItemId),
+            notify_update: impl FnMut(GroupId, ItemId),
+            mut report: impl FnMut(&'static str, ItemOutcome),
             #[cfg(feature = "crypt")] crypt_key_loader: impl Fn() -> Option<[u8; 32]>,
         ) -> bool {
-            let _s = tr::info_span!("load_node()", template=?ctx.template_name, path=?ctx.path);
-            let mut has_update = false;
+            let mut staged = StagedNode::default();
+            Self::stage_node(
+                ctx,
+                node,
+                &mut staged,
+                &mut report,
+                #[cfg(feature = "crypt")]
+                crypt_key_loader,
+            );
+
+            Self::commit_node(ctx, staged, notify_update, report)
+        }
+
+        /// Builds the value of every item found in `node`, without applying them.
+        fn stage_node(
+            ctx: &GroupContext,
+            node: &archive::Archive,
+            staged: &mut StagedNode,
+            mut report: impl FnMut(&'static str, ItemOutcome),
+            #[cfg(feature = "crypt")] crypt_key_loader: impl Fn() -> Option<[u8; 32]>,
+        ) {
+            let _s = tr::info_span!("stage_node()", template=?ctx.template_name, path=?ctx.path);
 
             #[cfg(feature = "crypt")]
             let mut crypt_key = None;
 
-            '_outer: for (elem, de) in ctx
-                .sources
-                .iter()
-                .filter(|e| !e.meta.flags.contains(MetaFlag::NO_IMPORT))
-                .filter_map(|x| node.values.get(x.meta.name).map(|o| (x, o)))
-            {
+            '_outer: for (index, elem, de) in ctx.sources.iter().enumerate().filter_map(|(i, x)| {
+                x.meta.archive_keys().find_map(|key| node.values.get(key)).map(|o| (i, x, o))
+            }) {
+                if elem.meta.flags.contains(MetaFlag::NO_IMPORT) {
+                    report(elem.meta.name, ItemOutcome::Skipped);
+                    continue;
+                }
+
                 let _s = tr::info_span!("node load", varname=?elem.meta.varname);
 
+                // Deprecated items are exported as well, thus only values other than the default
+                // are reported; otherwise every reload of an exported archive warns again.
+                if let Some(note) = elem.meta.deprecated {
+                    let default = elem.meta.vtable.create_default();
+                    if serde_json::to_value(default.as_serialize()).ok().as_ref() != Some(de) {
+                        tr::warn!(name = elem.meta.name, note, "Archive sets deprecated item");
+                    }
+                }
+
                 #[allow(unused_mut)]
-                let mut update_result = None;
+                let mut build_result = None;
+
+                #[allow(unused_mut)]
+                let mut decrypt_error: Option<String> = None;
 
                 #[cfg(feature = "crypt")]
                 'decryption: {
                     use aes_gcm::aead::{Aead, KeyInit};
-                    use base64::prelude::*;
 
                     if !elem.meta.flags.contains(MetaFlag::SECRET) {
                         // Just try to deserialize from plain value.
@@ -853,22 +1849,8 @@ mod inner {
                     // Non-string value is not an encrypted property. serve as-is.
                     let Some(str) = de.as_str() else { break 'decryption };
 
-                    // Verify if it is encrpyted string repr.
-                    if !str.starts_with(Self::CRYPT_PREFIX) {
-                        tr::debug!("Non-encrypted string repr. serve as-is.");
-                        break 'decryption;
-                    }
-
-                    let str = &str[Self::CRYPT_PREFIX.len()..];
-                    let Ok(bin) = BASE64_STANDARD_NO_PAD.decode(str).map_err(|error| {
-                        tr::debug!(
-                            %error,
-                            "Crypt-prefixed string is not valid base64. \
-                             Trying to parse as plain string."
-                        )
-                    }) else {
-                        break 'decryption;
-                    };
+                    // Verify if it is encrpyted string repr, in either format.
+                    let Some((nonce, bin)) = Self::crypt_decode(str) else { break 'decryption };
 
                     if crypt_key.is_none() {
                         crypt_key = Some(crypt_key_loader().ok_or(()));
@@ -878,37 +1860,182 @@ mod inner {
                     // string ... which woun't be very useful though.
                     let Ok(key) = crypt_key.as_ref().unwrap() else {
                         tr::warn!("Crypt key missing. Skipping secret data serialization.");
+                        decrypt_error = Some("Crypt key missing".into());
                         break 'decryption;
                     };
 
                     let cipher = aes_gcm::Aes256Gcm::new(key.into());
-                    let Ok(json) =
-                        cipher.decrypt(&Self::CRYPT_NONCE.into(), &bin[..]).map_err(|error| {
+                    let json = match cipher.decrypt(&nonce.into(), &bin[..]) {
+                        Ok(json) => json,
+                        Err(error) => {
                             tr::warn!(%error, "Failed to decrypt secret data");
-                        })
-                    else {
-                        break 'decryption;
+                            decrypt_error = Some(format!("Failed to decrypt: {error}"));
+                            break 'decryption;
+                        }
                     };
 
-                    update_result = Some(
-                        elem.update_value_from(&mut serde_json::Deserializer::from_slice(&json)),
+                    build_result = Some(
+                        elem.build_value_from(&mut serde_json::Deserializer::from_slice(&json)),
                     );
                 }
 
-                match update_result.unwrap_or_else(|| elem.update_value_from(de)) {
-                    Ok(_) => {
-                        has_update = true;
-                        notify_update(ctx.group_id, elem.id);
+                match build_result.unwrap_or_else(|| elem.build_value_from(de)) {
+                    Ok((value, validation)) => {
+                        let outcome = match validation {
+                            Validation::Valid => ItemOutcome::Applied,
+                            Validation::Modified => ItemOutcome::Clamped,
+                        };
+
+                        staged.stage(
+                            index,
+                            value,
+                            decrypt_error.map(ItemOutcome::DecryptFailed).unwrap_or(outcome),
+                        );
                     }
                     Err(error) => {
-                        tr::warn!(%error, "Element value update error during node loading")
+                        tr::warn!(%error, "Element value update error during node loading");
+
+                        let outcome = match &error {
+                            EntityUpdateError::DeserializeFailed(e) => {
+                                ItemOutcome::Rejected(format!("{error}: {e}"))
+                            }
+                            _ => ItemOutcome::Rejected(error.to_string()),
+                        };
+
+                        report(
+                            elem.meta.name,
+                            decrypt_error.map(ItemOutcome::DecryptFailed).unwrap_or(outcome),
+                        );
+                    }
+                }
+            }
+        }
+
+        /// Stages the template default of every importable item named in `removed`.
+        fn stage_reset(
+            ctx: &GroupContext,
+            removed: &[&str],
+            staged: &mut StagedNode,
+            mut report: impl FnMut(&'static str, ItemOutcome),
+        ) {
+            for (index, elem) in ctx.sources.iter().enumerate() {
+                if !removed.contains(&elem.meta.name) {
+                    continue;
+                }
+
+                if elem.meta.flags.contains(MetaFlag::NO_IMPORT) {
+                    report(elem.meta.name, ItemOutcome::Skipped);
+                    continue;
+                }
+
+                staged.stage(index, elem.meta.vtable.create_default(), ItemOutcome::Reset);
+            }
+        }
+
+        /// Validates the staged values as a whole with the template validator, and applies them
+        /// unless it rejects. Returns true if any value was applied.
+        fn commit_node(
+            ctx: &GroupContext,
+            staged: StagedNode,
+            notify_update: impl FnMut(GroupId, ItemId),
+            report: impl FnMut(&'static str, ItemOutcome),
+        ) -> bool {
+            Self::check_node(ctx, staged, report)
+                .is_some_and(|changes| Self::apply_node(ctx, changes, notify_update))
+        }
+
+        /// Validates the staged values as a whole with the template validator, and reports the
+        /// outcome of each. Returns the changes to apply, including the adjustments of the
+        /// validator, or `None` if it rejects.
+        fn check_node(
+            ctx: &GroupContext,
+            staged: StagedNode,
+            mut report: impl FnMut(&'static str, ItemOutcome),
+        ) -> Option<Vec<(usize, entity::EntityValue)>> {
+            let StagedNode { mut changes, outcomes } = staged;
+            let name_of = |index: usize| ctx.sources[index].meta.name;
+
+            let adjusted = match ctx.check_candidate(&mut changes) {
+                Ok(adjusted) => adjusted,
+                Err(error) => {
+                    tr::warn!(%error, "Template validation rejected loaded values");
+
+                    for (index, outcome) in outcomes {
+                        let outcome = match outcome {
+                            ItemOutcome::DecryptFailed(x) => ItemOutcome::DecryptFailed(x),
+                            _ => ItemOutcome::Rejected(format!(
+                                "Template validation failed: {error}"
+                            )),
+                        };
+
+                        report(name_of(index), outcome);
                     }
+
+                    return None;
+                }
+            };
+
+            // Items adjusted by the template validator are reported even if not imported.
+            for &index in adjusted.iter().filter(|&&i| outcomes.iter().all(|x| x.0 != i)) {
+                report(name_of(index), ItemOutcome::Clamped);
+            }
+
+            for (index, outcome) in outcomes {
+                let outcome = match outcome {
+                    ItemOutcome::Applied if adjusted.contains(&index) => ItemOutcome::Clamped,
+                    outcome => outcome,
+                };
+
+                report(name_of(index), outcome);
+            }
+
+            Some(changes)
+        }
+
+        /// Applies the changes checked by [`Inner::check_node`]. Returns true if there was any.
+        fn apply_node(
+            ctx: &GroupContext,
+            changes: Vec<(usize, entity::EntityValue)>,
+            mut notify_update: impl FnMut(GroupId, ItemId),
+        ) -> bool {
+            if changes.is_empty() {
+                return false;
+            }
+
+            for (index, value) in changes {
+                let elem = &ctx.sources[index];
+                elem.__apply_value(value);
+                notify_update(ctx.group_id, elem.id);
+            }
+
+            // On successful load, set its fence value as 1, to make the first client
+            //  side's call to `update()` call would be triggered.
+            ctx.version.fetch_add(1, Ordering::Release);
+
+            true
+        }
+
+        /// Applies the saved values which differ from the current ones.
+        fn restore_node(
+            ctx: &GroupContext,
+            values: &[entity::EntityValue],
+            mut notify_update: impl FnMut(GroupId, ItemId),
+        ) -> bool {
+            // Values are compared by their serialized form, since entities aren't comparable.
+            let json = |x: &entity::EntityValue| serde_json::to_value(x.as_serialize()).ok();
+            let mut has_update = false;
+
+            for (elem, value) in ctx.sources.iter().zip(values) {
+                if json(&elem.property_value().1) == json(value) {
+                    continue;
                 }
+
+                elem.__apply_value(value.clone());
+                has_update = true;
+                notify_update(ctx.group_id, elem.id);
             }
 
             if has_update {
-                // On successful load, set its fence value as 1, to make the first client
-                //  side's call to `update()` call would be triggered.
                 ctx.version.fetch_add(1, Ordering::Release);
             }
 
@@ -916,6 +2043,25 @@ mod inner {
         }
     }
 
+    /// Values of a single group which are about to be applied together.
+    #[derive(Default)]
+    struct StagedNode {
+        changes: Vec<(usize, entity::EntityValue)>,
+
+        /// Outcome of each staged value, reported once the whole set passes validation.
+        outcomes: Vec<(usize, ItemOutcome)>,
+    }
+
+    impl StagedNode {
+        /// Stages the value of the item at `index`, replacing the one staged before.
+        fn stage(&mut self, index: usize, value: entity::EntityValue, outcome: ItemOutcome) {
+            self.changes.retain(|x| x.0 != index);
+            self.outcomes.retain(|x| x.0 != index);
+            self.changes.push((index, value));
+            self.outcomes.push((index, outcome));
+        }
+    }
+
     /* ------------------------------------ Import Operation ------------------------------------ */
     #[derive(Setters)]
     #[setters(borrow_self)]
@@ -924,11 +2070,17 @@ mod inner {
         inner: &'a Inner,
 
         #[setters(skip)]
-        archive: ManuallyDrop<Archive>,
+        patch: Option<ArchivePatch>,
 
         /// When set to true, the imported config will be merged with the existing cache. This is typically
         /// useful to prevent unsaved archive entities from being overwritten.
         ///
+        /// If set to false, the imported config replaces the cache, and every item missing from it
+        /// is reset to the value of the persistent layers, or to its template default.
+        ///
+        /// In either case, values supplied by the transient layers keep precedence over the
+        /// imported ones. See [`Layer::is_transient`].
+        ///
         /// Default is `true`.
         merge_onto_cache: bool,
 
@@ -938,72 +2090,114 @@ mod inner {
         ///
         /// Default is `true`.
         apply_as_patch: bool,
+
+        /// If enabled, every change to the live groups is staged and validated first, including
+        /// the resets of removed items and the template validators, and nothing is applied unless
+        /// all of them pass and none of the imported values is unknown. Values of categories
+        /// without a live group are unknown as well. The groups can't be committed to meanwhile.
+        ///
+        /// Use [`ImportOnDrop::apply`] to find out whether the import was aborted.
+        ///
+        /// Default is `false`.
+        strict: bool,
     }
 
     impl<'a> ImportOnDrop<'a> {
-        pub(super) fn new(inner: &'a Inner, archive: Archive) -> Self {
+        pub(super) fn new(inner: &'a Inner, patch: ArchivePatch) -> Self {
             Self {
                 inner,
-                archive: ManuallyDrop::new(archive),
+                patch: Some(patch),
                 merge_onto_cache: true,
                 apply_as_patch: true,
+                strict: false,
             }
         }
     }
 
-    impl<'a> Drop for ImportOnDrop<'a> {
-        fn drop(&mut self) {
-            // SAFETY: Typical `ManuallyDrop` usage.
-            let mut imported = unsafe { ManuallyDrop::take(&mut self.archive) };
-            let this = self.inner;
+    /// Puts the value of `view` at every key of `keys` into the patch, so that all of them are
+    /// reloaded.
+    fn overlay(patch: &mut ArchivePatch, keys: &Archive, view: &Archive) {
+        for (key, _) in keys.iter_values() {
+            if let Some(value) = view.get_value(key) {
+                patch.insert_value(key, value.clone());
+            }
+        }
 
-            #[cfg(feature = "crypt")]
-            let key_loader = Inner::crypt_key_loader(&this.crypt_key);
+        for (key, child) in keys.iter_paths() {
+            if let Some(view) = view.get_path(key) {
+                overlay(patch.find_or_create_path_mut([key]), child, view);
+            }
+        }
+    }
 
-            let import_archive = |archive: &Archive| {
-                for group in this.all_groups.read().values() {
-                    let path = &group.context.path;
-                    let path = path.iter();
-                    let Some(node) = archive.find_path(path) else { continue };
+    impl<'a> ImportOnDrop<'a> {
+        /// Performs the import immediately, and reports the outcome of each item. Otherwise, the
+        /// import is performed on drop, discarding the report.
+        pub fn apply(&mut self) -> ImportReport {
+            #[allow(unused_mut)]
+            let Some(mut imported) = self.patch.take() else {
+                return Default::default();
+            };
+            let this = self.inner;
+            let layers = this.layers.read();
+            let mut cache = this.archive.write();
+            #[allow(unused_mut)]
+            let mut imported_values = imported.upserts();
 
-                    let mut updates = Vec::new();
+            #[cfg(feature = "crypt")]
+            let prev_salt = this.crypt_salt.read().clone();
 
-                    if Inner::load_node(
-                        &group.context,
-                        node,
-                        |g_id, e_id| updates.push((g_id, e_id)),
-                        #[cfg(feature = "crypt")]
-                        key_loader,
-                    ) {
-                        for (g_id, e_id) in updates {
-                            this._write_event_retained(|m| m.entity_value_updated(g_id, e_id));
-                        }
+            #[cfg(feature = "crypt")]
+            if this.adopt_crypt_salt(&mut imported_values) {
+                overlay(&mut imported, &imported_values, &imported_values);
+            }
 
-                        group.evt_on_update.notify();
-                    }
-                }
+            let next = if self.merge_onto_cache {
+                let mut next = cache.clone();
+                next.apply_patch(imported);
+                next
+            } else {
+                imported_values.clone()
             };
 
-            let mut self_archive = this.archive.write();
-            if self.apply_as_patch {
-                let patch = self_archive.create_patch(&mut imported);
-                import_archive(&patch);
+            // Values removed from the cache fall back to the ones of the persistent layers.
+            let next = layers.persistent().merge(next);
+
+            // Groups observe the cache through the transient layers, which keep precedence over
+            // the imported values.
+            let prev_view = layers.view(&cache);
+            let next_view = layers.view(&next);
+            let mut patch = prev_view.diff(&next_view);
+            if !self.apply_as_patch {
+                overlay(&mut patch, &next_view, &next_view);
+            } else if self.strict {
+                // Every imported value is checked, even if it's already cached.
+                overlay(&mut patch, &imported_values, &next_view);
+            }
 
-                if self.merge_onto_cache {
-                    self_archive.merge_from(patch);
-                } else {
-                    imported.merge_from(patch);
-                    *self_archive = imported;
-                }
-            } else {
-                if self.merge_onto_cache {
-                    self_archive.merge_from(imported);
-                } else {
-                    *self_archive = imported;
+            // Unknown keys are checked against the imported values, regardless of whether they
+            // changed or not.
+            let mut report = ImportReport::default();
+            this.find_unknown_keys(&imported_values, &mut report);
+
+            let origin = UpdateOrigin::Import;
+            if !this.apply_patch_to_groups(&patch, &prev_view, origin, self.strict, &mut report) {
+                #[cfg(feature = "crypt")]
+                {
+                    *this.crypt_salt.write() = prev_salt;
                 }
 
-                import_archive(&self_archive);
+                return ImportReport { aborted: true, ..report };
             }
+
+            *cache = next;
+            report
+        }
+    }
+
+    impl<'a> Drop for ImportOnDrop<'a> {
+        fn drop(&mut self) {
+            self.apply();
         }
     }
 
@@ -1040,18 +2234,37 @@ mod inner {
             let this = self.inner;
 
             #[cfg(feature = "crypt")]
-            let key_loader = Inner::crypt_key_loader(&this.crypt_key);
+            let key_loader = this.crypt_key_loader();
+
+            let groups = this.group_contexts();
+            let layers = this.layers.read();
+            let mut self_archive = this.archive.write();
 
-            for group in this.all_groups.read().values() {
+            // Values supplied by transient layers are exported as they'd be without them.
+            for ctx in &groups {
                 Inner::dump_node(
-                    &group.context,
+                    ctx,
                     &mut archive,
                     #[cfg(feature = "crypt")]
                     key_loader,
                 );
+
+                let base = self_archive.find_path(ctx.path.iter());
+                Inner::unpin_transient(&layers, ctx, &mut archive, base);
             }
 
-            let mut self_archive = this.archive.write();
+            drop(layers);
+
+            #[cfg(feature = "crypt")]
+            this.write_crypt_salt(&mut archive);
+
+            let version = this.migrations.read().current();
+            if version > 0 {
+                archive.insert_value(migrate::SCHEMA_VERSION_KEY, version.into());
+            }
+
+            this.remove_previous_names(&mut self_archive);
+
             if !self.merge_onto_dumped {
                 if self.replace_import_cache {
                     *self_archive = archive;
@@ -0,0 +1,246 @@
+//! Hot reloading of configuration files.
+//!
+//! [`FileWatcher`] polls one or more files, and whenever any of them changes, re-imports the
+//! merged content of all files into the storage as a patch. Therefore, only the values which are
+//! actually changed fire update notifications.
+//!
+//! Files are merged in the order they were given; values of the later files take precedence.
+//! Values removed from the merged content are reset to their template defaults.
+//! Changes written by a [`FileBackend`](super::file::FileBackend) of this process are ignored.
+
+use std::{fs, io, path::PathBuf, sync::Arc, thread::JoinHandle, time::Duration};
+
+use derive_setters::Setters;
+use parking_lot::{Condvar, Mutex};
+
+use crate::shared::archive::Archive;
+
+use super::{
+    codec::Format,
+    file::{self, FileBackendError},
+    storage::Storage,
+};
+
+/// Handler of reload errors. Called from the watcher thread.
+pub type ReloadErrorHandler = Arc<dyn Fn(&FileBackendError) + Send + Sync>;
+
+/// Options of [`FileWatcher`].
+#[derive(Clone, Setters)]
+#[non_exhaustive]
+pub struct FileWatcherOptions {
+    /// Interval between file modification checks.
+    ///
+    /// Default is 500 ms.
+    pub poll_interval: Duration,
+
+    /// Format of every watched file. If not specified, it's detected from each file extension.
+    #[setters(strip_option)]
+    pub format: Option<Format>,
+
+    /// Called whenever a file fails to be read or parsed. Values previously loaded from the file
+    /// are retained in that case. Errors are logged regardless of this handler.
+    #[setters(strip_option)]
+    pub on_error: Option<ReloadErrorHandler>,
+}
+
+impl Default for FileWatcherOptions {
+    fn default() -> Self {
+        Self { poll_interval: Duration::from_millis(500), format: None, on_error: None }
+    }
+}
+
+/// Watches configuration files and imports their changes into a storage. See module
+/// documentation.
+///
+/// Watching stops once dropped.
+pub struct FileWatcher {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    storage: Storage,
+    options: FileWatcherOptions,
+    files: Mutex<Vec<WatchedFile>>,
+
+    /// Merged content of every file, as of the last import.
+    merged: Mutex<Archive>,
+
+    closed: Mutex<bool>,
+    wake: Condvar,
+}
+
+struct WatchedFile {
+    path: PathBuf,
+    format: Format,
+
+    /// Hash of the last observed content.
+    hash: Option<u64>,
+
+    /// Last successfully parsed content.
+    archive: Archive,
+}
+
+impl FileWatcher {
+    /// Starts watching given files with default options. See [`FileWatcher::watch_with`].
+    pub fn watch(
+        storage: &Storage,
+        paths: impl IntoIterator<Item = impl Into<PathBuf>>,
+    ) -> Result<Self, FileBackendError> {
+        Self::watch_with(storage, paths, Default::default())
+    }
+
+    /// Loads given files into the storage, then starts watching them.
+    ///
+    /// Missing files are allowed, and are loaded once they're created. Files which fail to be
+    /// parsed are reported as same as any later reload error.
+    pub fn watch_with(
+        storage: &Storage,
+        paths: impl IntoIterator<Item = impl Into<PathBuf>>,
+        options: FileWatcherOptions,
+    ) -> Result<Self, FileBackendError> {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let path = path.into();
+                let format = options
+                    .format
+                    .or_else(|| Format::from_path(&path))
+                    .ok_or_else(|| FileBackendError::UnknownFormat(path.clone()))?;
+
+                Ok(WatchedFile { path, format, hash: None, archive: Default::default() })
+            })
+            .collect::<Result<_, FileBackendError>>()?;
+
+        let shared = Arc::new(Shared {
+            storage: storage.clone(),
+            options,
+            files: Mutex::new(files),
+            merged: Default::default(),
+            closed: Mutex::new(false),
+            wake: Default::default(),
+        });
+
+        shared.check();
+
+        let worker = std::thread::Builder::new()
+            .name("config-it-file-watcher".into())
+            .spawn({
+                let shared = shared.clone();
+                move || shared.run()
+            })
+            .map_err(|source| {
+                let path = shared.files.lock().first().map(|x| x.path.clone()).unwrap_or_default();
+                FileBackendError::Io { path, source }
+            })?;
+
+        Ok(Self { shared, worker: Some(worker) })
+    }
+
+    /// Checks every watched file immediately, instead of waiting for next poll. Returns true if
+    /// any change was imported.
+    pub fn check_now(&self) -> bool {
+        self.shared.check()
+    }
+
+    /// Paths being watched.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.shared.files.lock().iter().map(|x| x.path.clone()).collect()
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        *self.shared.closed.lock() = true;
+        self.shared.wake.notify_all();
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Shared {
+    fn run(&self) {
+        let mut closed = self.closed.lock();
+
+        while !*closed {
+            self.wake.wait_for(&mut closed, self.options.poll_interval);
+
+            if !*closed {
+                parking_lot::MutexGuard::unlocked(&mut closed, || self.check());
+            }
+        }
+    }
+
+    /// Reloads changed files, and imports merged content if there's any change.
+    fn check(&self) -> bool {
+        let mut files = self.files.lock();
+        let mut changed = false;
+
+        for file in files.iter_mut() {
+            match file.poll() {
+                Ok(x) => changed |= x,
+                Err(error) => {
+                    tr::warn!(%error, "failed to reload config file");
+
+                    if let Some(handler) = &self.options.on_error {
+                        handler(&error);
+                    }
+                }
+            }
+        }
+
+        if !changed {
+            return false;
+        }
+
+        let merged = files.iter().fold(Archive::default(), |acc, x| {
+            let mut archive = x.archive.clone();
+            self.storage.migrate(&mut archive);
+            acc.merge(archive)
+        });
+        let patch = std::mem::replace(&mut *self.merged.lock(), merged.clone()).diff(&merged);
+        drop(files);
+
+        self.storage.import_patch(patch).apply_as_patch(true);
+        true
+    }
+}
+
+impl WatchedFile {
+    /// Reloads the file if it has been modified. Returns true if parsed content was replaced.
+    fn poll(&mut self) -> Result<bool, FileBackendError> {
+        // Files are read on every poll; modification time is too coarse on some file systems to
+        // catch consecutive writes.
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                // Keep values of deleted file; it may be in the middle of being replaced.
+                return Ok(false);
+            }
+            Err(source) => return Err(FileBackendError::Io { path: self.path.clone(), source }),
+        };
+
+        let hash = file::content_hash(text.as_bytes());
+
+        if self.hash == Some(hash) {
+            return Ok(false);
+        }
+
+        self.hash = Some(hash);
+
+        if file::is_self_write(&self.path, text.as_bytes()) {
+            tr::debug!(path = ?self.path, "skipping reload of self-written content");
+            self.archive = self.format.decode(&text).unwrap_or_default();
+            return Ok(false);
+        }
+
+        self.archive = self
+            .format
+            .decode(&text)
+            .map_err(|source| FileBackendError::Decode { path: self.path.clone(), source })?;
+
+        Ok(true)
+    }
+}
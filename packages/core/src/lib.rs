@@ -133,9 +133,10 @@ mod config_export {
 
     pub use entity::{Validation, ValidationResult};
 
-    pub use archive::Archive;
+    pub use archive::{Archive, ArchivePatch};
     pub use group::{Group, Template};
-    pub use storage::{Monitor, Storage};
+    pub use layer::Layer;
+    pub use storage::{Monitor, Snapshot, Storage};
 
     #[cfg(feature = "arc-swap")]
     pub use storage::atomic::AtomicStorageArc;
@@ -9,6 +9,16 @@ type Map<T, V> = std::collections::BTreeMap<T, V>;
 #[cfg(feature = "indexmap")]
 type Map<T, V> = indexmap::IndexMap<T, V>;
 
+/// Removes the entry while keeping the order of the others, as `IndexMap::remove` swaps the last
+/// entry into its place.
+fn map_remove<V>(map: &mut Map<CompactString, V>, key: &str) -> Option<V> {
+    #[cfg(feature = "indexmap")]
+    return map.shift_remove(key);
+
+    #[cfg(not(feature = "indexmap"))]
+    map.remove(key)
+}
+
 /// Defines rules for serializing category names within the [`Archive`].
 ///
 /// When an [`Archive`] is serialized, it manifests as a map of key-value pairs, where the key is a
@@ -62,7 +72,7 @@ pub fn with_category_rule(rule: CategoryRule, f: impl FnOnce() + std::panic::Unw
     CATEGORY_RULE.with(|x| unsafe {
         // SAFETY: Temporarily override lifetime as &'static; The `x` is guaranteed to be restored
         //         to its original value on function exit, even if a panic occurs.
-        x.replace(std::mem::transmute(rule));
+        x.replace(std::mem::transmute::<CategoryRule<'_>, CategoryRule<'static>>(rule));
 
         let err = std::panic::catch_unwind(|| {
             f();
@@ -180,11 +190,11 @@ impl Archive {
     }
 
     pub fn remove_value(&mut self, key: &str) -> Option<serde_json::Value> {
-        self.values.remove(key)
+        map_remove(&mut self.values, key)
     }
 
     pub fn remove_path(&mut self, key: &str) -> Option<Archive> {
-        self.paths.remove(key)
+        map_remove(&mut self.paths, key)
     }
 
     pub fn clear_values(&mut self) {
@@ -244,6 +254,21 @@ impl Archive {
         node
     }
 
+    /// Mutable counterpart of [`Archive::find_path`].
+    pub fn find_path_mut<'a, T: AsRef<str> + 'a>(
+        &mut self,
+        path: impl IntoIterator<Item = T>,
+    ) -> Option<&mut Archive> {
+        let mut iter = path.into_iter();
+        let mut node = self.paths.get_mut(iter.next()?.as_ref())?;
+
+        for key in iter {
+            node = node.paths.get_mut(key.as_ref())?;
+        }
+
+        Some(node)
+    }
+
     /// Retrieves a mutable reference to a nested category, creating it if it doesn't exist.
     ///
     /// This method is useful for ensuring a category exists at a certain path, creating any
@@ -355,6 +380,180 @@ impl Archive {
     }
 }
 
+impl Archive {
+    /// Generates a patch which turns the current archive into `newer`.
+    ///
+    /// Unlike [`Archive::create_patch`], values and categories missing from `newer` are recorded
+    /// as removals, and `newer` is left untouched.
+    pub fn diff(&self, newer: &Self) -> ArchivePatch {
+        let mut patch = ArchivePatch::default();
+
+        for (k, v) in &self.paths {
+            match newer.paths.get(k) {
+                Some(newer_v) => {
+                    let patch_v = v.diff(newer_v);
+                    if !patch_v.is_empty() {
+                        patch.paths.insert(k.clone(), Some(patch_v));
+                    }
+                }
+                None => {
+                    patch.paths.insert(k.clone(), None);
+                }
+            }
+        }
+
+        for (k, v) in newer.paths.iter().filter(|(k, _)| !self.paths.contains_key(*k)) {
+            patch.paths.insert(k.clone(), Some(v.clone().into()));
+        }
+
+        for k in self.values.keys().filter(|k| !newer.values.contains_key(*k)) {
+            patch.values.insert(k.clone(), None);
+        }
+
+        for (k, v) in &newer.values {
+            if self.values.get(k) != Some(v) {
+                patch.values.insert(k.clone(), Some(v.clone()));
+            }
+        }
+
+        patch
+    }
+
+    /// Applies a patch, including its removals. Categories which are added by the patch are
+    /// created.
+    pub fn apply_patch(&mut self, patch: ArchivePatch) {
+        for (k, v) in patch.paths {
+            match v {
+                Some(v) => self.paths.entry(k).or_default().apply_patch(v),
+                None => {
+                    self.remove_path(&k);
+                }
+            }
+        }
+
+        for (k, v) in patch.values {
+            match v {
+                Some(v) => {
+                    self.values.insert(k, v);
+                }
+                None => {
+                    self.remove_value(&k);
+                }
+            }
+        }
+    }
+}
+
+/// Difference between two archives, which can express removals as well.
+///
+/// Each value or category is either replaced, or marked with a tombstone which removes it. Created
+/// by [`Archive::diff`], and applied by [`Archive::apply_patch`]. An [`Archive`] converts into a
+/// patch without any removal.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct ArchivePatch {
+    /// Patches of child categories. `None` removes the category.
+    pub(crate) paths: Map<CompactString, Option<ArchivePatch>>,
+
+    /// Replaced values. `None` removes the value.
+    pub(crate) values: Map<CompactString, Option<serde_json::Value>>,
+}
+
+impl ArchivePatch {
+    pub fn iter_values(&self) -> impl Iterator<Item = (&str, Option<&serde_json::Value>)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_ref()))
+    }
+
+    pub fn iter_paths(&self) -> impl Iterator<Item = (&str, Option<&ArchivePatch>)> {
+        self.paths.iter().map(|(k, v)| (k.as_str(), v.as_ref()))
+    }
+
+    pub fn insert_value(&mut self, key: impl ToCompactString, value: serde_json::Value) {
+        self.values.insert(key.to_compact_string(), Some(value));
+    }
+
+    pub fn insert_path(&mut self, key: impl ToCompactString, value: ArchivePatch) {
+        self.paths.insert(key.to_compact_string(), Some(value));
+    }
+
+    /// Marks the value as removed.
+    pub fn tombstone_value(&mut self, key: impl ToCompactString) {
+        self.values.insert(key.to_compact_string(), None);
+    }
+
+    /// Marks the category as removed, along with everything under it.
+    pub fn tombstone_path(&mut self, key: impl ToCompactString) {
+        self.paths.insert(key.to_compact_string(), None);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.values.is_empty()
+    }
+
+    /// Retrieves a mutable reference to a nested category patch, creating it if it doesn't exist.
+    /// A removed category on the way is replaced with an empty patch.
+    pub fn find_or_create_path_mut<'s, 'a>(
+        &'s mut self,
+        path: impl IntoIterator<Item = &'a str>,
+    ) -> &'s mut ArchivePatch {
+        path.into_iter().fold(self, |node, key| {
+            node.paths.entry(key.into()).or_default().get_or_insert_with(Default::default)
+        })
+    }
+
+    /// Collects every replaced value and added category, dropping removals.
+    pub fn upserts(&self) -> Archive {
+        Archive {
+            paths: (self.paths.iter())
+                .filter_map(|(k, v)| Some((k.clone(), v.as_ref()?.upserts())))
+                .collect(),
+            values: (self.values.iter())
+                .filter_map(|(k, v)| Some((k.clone(), v.clone()?)))
+                .collect(),
+        }
+    }
+
+    /// Names of the values under `path` which are removed by this patch, when it's applied onto
+    /// `base`. Every value of `base` under a removed category is counted as removed.
+    pub(crate) fn removed_values_at<'s, T: AsRef<str>>(
+        &'s self,
+        base: &'s Archive,
+        path: impl IntoIterator<Item = T>,
+    ) -> Vec<&'s str> {
+        let mut patch = Some(self);
+        let mut base = Some(base);
+
+        for key in path {
+            let key = key.as_ref();
+            base = base.and_then(|x| x.paths.get(key));
+
+            if let Some(node) = patch {
+                match node.paths.get(key) {
+                    Some(Some(child)) => patch = Some(child),
+                    Some(None) => patch = None,
+                    None => return Vec::new(),
+                }
+            }
+        }
+
+        match (patch, base) {
+            (Some(node), _) => {
+                node.values.iter().filter(|(_, v)| v.is_none()).map(|(k, _)| k.as_str()).collect()
+            }
+            (None, Some(base)) => base.values.keys().map(|k| k.as_str()).collect(),
+            (None, None) => Vec::new(),
+        }
+    }
+}
+
+impl From<Archive> for ArchivePatch {
+    fn from(value: Archive) -> Self {
+        Self {
+            paths: value.paths.into_iter().map(|(k, v)| (k, Some(v.into()))).collect(),
+            values: value.values.into_iter().map(|(k, v)| (k, Some(v))).collect(),
+        }
+    }
+}
+
 impl<'a> Deserialize<'a> for Archive {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -520,3 +719,50 @@ fn test_archive_basic() {
     assert!(val.contains_key("valll"));
     assert!(val.get("valll") == Some(&serde_json::Value::from(4.44)));
 }
+
+#[test]
+fn test_archive_diff() {
+    let prev: Archive = serde_json::from_str(
+        r#"{ "~a": { "x": 1, "y": 2, "~b": { "z": 3 } }, "~c": { "w": 4 }, "r": 0 }"#,
+    )
+    .unwrap();
+    let next: Archive =
+        serde_json::from_str(r#"{ "~a": { "x": 5, "~b": { "z": 3 } }, "~d": {}, "r": 0 }"#)
+            .unwrap();
+
+    let patch = prev.diff(&next);
+    assert_eq!(patch.paths.len(), 3);
+    assert!(patch.values.is_empty());
+    assert_eq!(patch.paths["c"], None);
+    assert_eq!(patch.paths["d"], Some(ArchivePatch::default()));
+
+    let a = patch.paths["a"].as_ref().unwrap();
+    assert_eq!(a.values["x"], Some(5.into()));
+    assert_eq!(a.values["y"], None);
+    assert!(a.paths.is_empty());
+
+    assert_eq!(patch.removed_values_at(&prev, ["a"]), ["y"]);
+    assert_eq!(patch.removed_values_at(&prev, ["c"]), ["w"]);
+    assert!(patch.removed_values_at(&prev, ["a", "b"]).is_empty());
+    assert!(patch.upserts().find_path(["c"]).is_none());
+
+    let mut applied = prev.clone();
+    applied.apply_patch(patch);
+    assert_eq!(applied, next);
+    assert!(prev.diff(&prev).is_empty());
+}
+
+#[test]
+#[cfg(feature = "indexmap")]
+fn test_archive_remove_keeps_order() {
+    let mut archive: Archive =
+        serde_json::from_str(r#"{ "~a": { "x": 1, "y": 2, "z": 3 }, "~b": {}, "~c": {} }"#)
+            .unwrap();
+
+    archive.remove_path("a");
+    assert!(archive.iter_paths().map(|x| x.0).eq(["b", "c"]));
+
+    let mut archive: Archive = serde_json::from_str(r#"{ "x": 1, "y": 2, "z": 3 }"#).unwrap();
+    archive.remove_value("x");
+    assert!(archive.iter_values().map(|x| x.0).eq(["y", "z"]));
+}
@@ -107,10 +107,19 @@ pub struct Metadata {
 
     /// Corresponding environment variable name, if any, that maps to this configuration entity.
     pub env: Option<&'static str>,
+
+    /// Deprecation notice, e.g. which entity replaces this one. Monitors may render deprecated
+    /// entities differently, and imports warn whenever an archive still sets them.
+    pub deprecated: Option<&'static str>,
+
+    /// Legacy names of this entity, which are still accepted on import. Only `name` is exported.
+    #[serde(skip_deserializing)]
+    pub previous_names: &'static [&'static str],
 }
 
 impl Metadata {
     #[doc(hidden)]
+    #[allow(clippy::too_many_arguments)]
     pub fn __macro_new(
         name: &'static str,
         varname: &'static str,
@@ -119,6 +128,8 @@ impl Metadata {
         editor_hint: Option<MetadataEditorHint>,
         description: &'static str,
         env: Option<&'static str>,
+        deprecated: Option<&'static str>,
+        previous_names: &'static [&'static str],
         #[cfg(feature = "jsonschema")] schema: Option<crate::Schema>,
     ) -> Self {
         Self {
@@ -131,6 +142,14 @@ impl Metadata {
             schema,
             description,
             env,
+            deprecated,
+            previous_names,
         }
     }
+
+    /// Archive keys which are read as this entity on import, in order of precedence: the current
+    /// name first, then the previous names.
+    pub fn archive_keys(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(self.name).chain(self.previous_names.iter().copied())
+    }
 }
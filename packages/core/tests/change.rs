@@ -0,0 +1,75 @@
+#![cfg(feature = "config-derive")]
+
+use config_it::{
+    config::{
+        change::{ChangeEvent, ChangeNotice, MAX_PENDING_EVENTS},
+        storage::UpdateOrigin,
+    },
+    Archive,
+};
+use futures::StreamExt;
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    #[config(default = 80)]
+    port: u16,
+
+    #[config(default = "", secret)]
+    token: String,
+}
+
+fn changed(notice: Option<ChangeNotice>) -> ChangeEvent {
+    match notice {
+        Some(ChangeNotice::Changed(event)) => event,
+        other => panic!("expected change event, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn change_events() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["net", "server"]).unwrap();
+    let mut changes = storage.subscribe_changes();
+
+    server.port = 8080;
+    server.commit_elem(&server.port, true);
+
+    let event = changed(changes.try_recv().ok());
+    assert_eq!(event.path.iter().collect::<Vec<_>>(), ["net", "server"]);
+    assert_eq!((event.name, event.origin), ("port", UpdateOrigin::Commit));
+    assert_eq!((event.old, event.new), (80.into(), 8080.into()));
+    assert!(changes.try_recv().is_err());
+
+    let archive: Archive =
+        serde_json::from_str(r#"{ "~net": { "~server": { "port": 1, "token": "x" } } }"#).unwrap();
+    storage.import(archive);
+
+    let mut events = [changed(changes.next().await), changed(changes.next().await)];
+    events.sort_by_key(|x| x.name);
+    assert_eq!((events[0].name, &events[0].new), ("port", &1.into()));
+    assert!(events[0].version > event.version);
+    assert_eq!((events[1].name, &events[1].new), ("token", &serde_json::Value::Null));
+    assert!(events.iter().all(|x| x.origin == UpdateOrigin::Import));
+
+    // Stream ends once the storage is dropped.
+    drop((storage, server));
+    assert!(changes.next().await.is_none());
+}
+
+#[test]
+fn change_events_overflow() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["server"]).unwrap();
+    let mut changes = storage.subscribe_changes();
+
+    for port in 0..=MAX_PENDING_EVENTS as u16 {
+        server.port = port;
+        server.commit_elem(&server.port, true);
+    }
+
+    // Pending events collapse into single marker, followed by the ones sent afterwards.
+    assert!(matches!(changes.try_recv(), Ok(ChangeNotice::Overflow)));
+    let event = changed(changes.try_recv().ok());
+    assert_eq!(event.new, serde_json::Value::from(MAX_PENDING_EVENTS));
+    assert!(changes.try_recv().is_err());
+}
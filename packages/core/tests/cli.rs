@@ -0,0 +1,78 @@
+#![cfg(feature = "config-derive")]
+
+use config_it::config::cli::{CliError, CommandLine};
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    /// Port to listen on.
+    #[config(default = 80, previous_names = ["listen_port"])]
+    port: u16,
+
+    #[config(default = "localhost")]
+    host: String,
+
+    #[config(default = false)]
+    verbose: bool,
+
+    #[config(default = 0, transient)]
+    sessions: u32,
+}
+
+#[test]
+fn parse_args() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["net", "server"]).unwrap();
+
+    let cli = CommandLine::from_storage(&storage);
+    let archive = cli
+        .parse([
+            "--net.server.listen_port=8080",
+            "--net.server.host",
+            "example.com",
+            "--net.server.verbose",
+            "--",
+            "--ignored",
+        ])
+        .unwrap();
+
+    let node = archive.find_path(["net", "server"]).unwrap();
+    assert_eq!(node.get_value("port"), Some(&8080.into()));
+    assert_eq!(node.get_value("host"), Some(&"example.com".into()));
+
+    storage.import(archive);
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str(), server.verbose), (8080, "example.com", true));
+
+    // `--set` takes strict JSON.
+    let archive = cli.parse(["--set", "net.server.host=\"a\""]).unwrap();
+    assert_eq!(archive.find_path(["net", "server"]).unwrap().get_value("host"), Some(&"a".into()));
+    assert!(matches!(
+        cli.parse(["--set", "net.server.host=a"]),
+        Err(CliError::InvalidValue { .. })
+    ));
+}
+
+#[test]
+fn reject_args() {
+    let mut cli = CommandLine::new();
+    cli.add_template::<Server>(["server"]);
+
+    let err = cli.parse(["--server.port=http"]).unwrap_err();
+    assert!(
+        matches!(&err, CliError::InvalidValue { key, type_name: "u16", .. } if key == "server.port")
+    );
+
+    assert!(
+        matches!(cli.parse(["--server.prot=1"]), Err(CliError::UnknownKey(x)) if x == "server.prot")
+    );
+    assert!(matches!(cli.parse(["--server.sessions=1"]), Err(CliError::UnknownKey(_))));
+    assert!(matches!(cli.parse(["--server.port"]), Err(CliError::MissingValue(_))));
+    assert!(matches!(cli.parse(["positional"]), Err(CliError::UnexpectedArgument(_))));
+    assert!(matches!(cli.parse(["-h"]), Err(CliError::Help)));
+
+    let help = cli.help();
+    assert!(
+        help.contains("  --server.port=<u16>\n        Port to listen on.\n        Default: 80\n")
+    );
+    assert!(!help.contains("sessions"));
+}
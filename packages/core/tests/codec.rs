@@ -0,0 +1,198 @@
+#![cfg(feature = "config")]
+
+use config_it::{config::codec::Format, Archive};
+
+fn sample() -> Archive {
+    serde_json::from_value(serde_json::json!({
+        "root_value": "at root",
+        "~window": {
+            "width": 800,
+            "scale": 1.5,
+            "title": "main \"window\"",
+            "visible": true,
+            "tags": ["a", "b"],
+            "margin": { "left": 1, "right": [2, 3] },
+            "~position": { "x": -10, "y": 20 },
+            "~empty": {}
+        },
+        "~net": {
+            "~proxy": { "host": "localhost", "port": "8080" }
+        }
+    }))
+    .unwrap()
+}
+
+#[allow(dead_code)]
+fn roundtrip(format: Format) -> String {
+    let archive = sample();
+    let text = format.encode(&archive).unwrap();
+    assert!(!text.contains('~'), "{text}");
+    assert_eq!(format.decode(&text).unwrap(), archive, "{text}");
+    text
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn toml_codec() {
+    let text = roundtrip(Format::Toml);
+    assert!(text.contains("[window.position]"));
+
+    let archive = Format::Toml
+        .decode(indoc::indoc! {r#"
+            [server]
+            port = 80 # comment
+            limits = { rate = 10 }
+
+            [[server.routes]]
+            path = "/"
+        "#})
+        .unwrap();
+
+    let server = archive.find_path(["server"]).unwrap();
+    assert_eq!(server.get_value("port"), Some(&80.into()));
+    assert_eq!(server.get_value("limits"), Some(&serde_json::json!({ "rate": 10 })));
+    assert_eq!(server.get_value("routes"), Some(&serde_json::json!([{ "path": "/" }])));
+    assert_eq!(Format::from_extension("TOML"), Some(Format::Toml));
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn yaml_codec() {
+    let text = roundtrip(Format::Yaml);
+    assert!(text.contains("!value"));
+
+    let archive = Format::Yaml
+        .decode(indoc::indoc! {r#"
+            server:
+              port: 80
+              limits: !value { rate: 10 }
+              hosts: [a, b]
+        "#})
+        .unwrap();
+
+    let server = archive.find_path(["server"]).unwrap();
+    assert_eq!(server.get_value("port"), Some(&80.into()));
+    assert_eq!(server.get_value("limits"), Some(&serde_json::json!({ "rate": 10 })));
+    assert_eq!(server.get_value("hosts"), Some(&serde_json::json!(["a", "b"])));
+    assert_eq!(Format::from_extension("yml"), Some(Format::Yaml));
+}
+
+#[test]
+#[cfg(feature = "ini")]
+fn ini_codec() {
+    let text = roundtrip(Format::Ini);
+    assert!(text.contains("[net.proxy]"));
+    assert!(!text.contains("[net]\n"));
+
+    let archive = Format::Ini
+        .decode(indoc::indoc! {r#"
+            ; comment
+            [server]
+            name = plain text
+            port = 80
+
+            [server.tls]
+            enabled = true
+        "#})
+        .unwrap();
+
+    let server = archive.find_path(["server"]).unwrap();
+    assert_eq!(server.get_value("name"), Some(&"plain text".into()));
+    assert_eq!(server.get_value("port"), Some(&80.into()));
+    assert_eq!(
+        archive.find_path(["server", "tls"]).unwrap().get_value("enabled"),
+        Some(&true.into())
+    );
+
+    let deep: Archive = serde_json::from_str(r#"{ "~a": { "~b": { "~c": {} } } }"#).unwrap();
+    assert!(Format::Ini.encode(&deep).is_err());
+    assert!(Format::Ini.decode("[a.b.c]").is_err());
+}
+
+#[allow(dead_code)]
+fn describe(path: &[&str], name: &str) -> Option<String> {
+    (name == "height").then(|| format!("Height of {}", path.join(".")))
+}
+
+#[allow(dead_code)]
+fn patch_sample() -> Archive {
+    serde_json::from_value(serde_json::json!({
+        "~window": { "width": 1024, "height": 600 },
+        "~net": { "~proxy": { "port": 3128 } }
+    }))
+    .unwrap()
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn toml_patch_keeps_comments() {
+    let original = indoc::indoc! {r#"
+        # Window settings
+        [window]
+        width = 800 # pixels
+        title = "main"
+    "#};
+
+    let text = Format::Toml.patch_document(original, &patch_sample(), &describe).unwrap();
+    assert!(text.starts_with("# Window settings\n[window]\nwidth = 1024 # pixels\n"), "{text}");
+    assert!(text.contains("# Height of window\nheight = 600\n"), "{text}");
+    assert!(text.contains("[net.proxy]\nport = 3128"), "{text}");
+
+    let expected = Format::Toml.decode(original).unwrap().merge(patch_sample());
+    assert_eq!(Format::Toml.decode(&text).unwrap(), expected);
+    assert_eq!(Format::Toml.patch_document(&text, &Archive::default(), &describe).unwrap(), text);
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn yaml_patch_keeps_comments() {
+    let original = indoc::indoc! {r#"
+        # Window settings
+        window:
+          width: 800 # pixels
+          title: main
+        net: {}
+    "#};
+
+    let text = Format::Yaml.patch_document(original, &patch_sample(), &describe).unwrap();
+    assert!(text.starts_with("# Window settings\nwindow:\n  width: 1024 # pixels\n"), "{text}");
+    assert!(text.contains("  title: main\n  # Height of window\n  height: 600\n"), "{text}");
+    assert!(text.contains("net:\n  proxy:\n    port: 3128\n"), "{text}");
+
+    let expected = Format::Yaml.decode(original).unwrap().merge(patch_sample());
+    assert_eq!(Format::Yaml.decode(&text).unwrap(), expected);
+}
+
+#[test]
+#[cfg(feature = "ini")]
+fn ini_patch_keeps_comments() {
+    let original = indoc::indoc! {r#"
+        ; Window settings
+        [window]
+        width=800
+        title = main
+
+        [other]
+        key = 1
+    "#};
+
+    let text = Format::Ini.patch_document(original, &patch_sample(), &describe).unwrap();
+    assert!(
+        text.starts_with(
+            "; Window settings\n[window]\nwidth=1024\ntitle = main\n; Height of window\n"
+        ),
+        "{text}"
+    );
+    assert!(text.ends_with("key = 1\n\n[net.proxy]\nport = 3128\n"), "{text}");
+
+    let expected = Format::Ini.decode(original).unwrap().merge(patch_sample());
+    assert_eq!(Format::Ini.decode(&text).unwrap(), expected);
+}
+
+#[test]
+fn json_patch_rewrites() {
+    let original = r#"{ "~window": { "width": 800 } }"#;
+    let text = Format::Json.patch_document(original, &patch_sample(), &describe).unwrap();
+    let expected = Format::Json.decode(original).unwrap().merge(patch_sample());
+    assert_eq!(Format::Json.decode(&text).unwrap(), expected);
+}
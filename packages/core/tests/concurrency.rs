@@ -69,3 +69,115 @@ fn tick(storage: config_it::Storage) {
         commit_elem!(group, notify(var, varg, vk, tew));
     }
 }
+
+#[derive(config_it::Template, Clone)]
+struct Endpoint {
+    #[config(default = "h0")]
+    host: String,
+
+    #[config(default = 0)]
+    port: u32,
+
+    #[config(max = 100)]
+    weight: u32,
+
+    #[config(default = "tcp", one_of = ["tcp", "udp"])]
+    proto: String,
+}
+
+#[test]
+fn transaction_is_atomic() {
+    let storage = config_it::create_storage();
+    let mut writer = storage.create::<Endpoint>(["endpoint"]).unwrap().updated();
+
+    let reader = std::thread::spawn({
+        let mut reader = storage.find::<Endpoint>(["endpoint"]).unwrap();
+        move || {
+            while reader.port < 1000 {
+                if reader.update() {
+                    assert_eq!(reader.host, format!("h{}", reader.port));
+                }
+            }
+        }
+    });
+
+    for port in 1..=1000 {
+        let result = writer.transaction(|x| {
+            x.host = format!("h{port}");
+            x.port = port;
+        });
+        assert!(result.is_ok());
+    }
+
+    reader.join().unwrap();
+
+    // Single rejected value discards the whole transaction.
+    writer.host = "rejected".into();
+    writer.proto = "sctp".into();
+    assert!(writer.commit_all(true).is_err());
+
+    let mut other = storage.find::<Endpoint>(["endpoint"]).unwrap().updated();
+    assert_eq!((other.host.as_str(), other.proto.as_str()), ("h1000", "tcp"));
+
+    writer.proto = "udp".into();
+    writer.weight = 101;
+    assert_eq!(writer.commit_all(true), Ok(config_it::Validation::Modified));
+    assert!(other.update());
+    assert_eq!((other.host.as_str(), other.proto.as_str(), other.weight), ("rejected", "udp", 100));
+}
+
+#[tokio::test]
+async fn watch_field() {
+    use futures::StreamExt;
+
+    let storage = config_it::create_storage();
+    let mut group = storage.create::<Foo>(["watch"]).unwrap().updated();
+    let mut values = group.watch_field(&group.var);
+
+    // Changes of other fields are skipped, and intermediate values are coalesced.
+    group.vk = "changed".into();
+    commit_elem!(group, notify(vk));
+    group.var = 1;
+    commit_elem!(group, notify(var));
+    group.var = 2;
+    commit_elem!(group, notify(var));
+    assert_eq!(values.next().await, Some(2));
+
+    let task = tokio::spawn(async move { values.next().await });
+    tokio::task::yield_now().await;
+    group.var = 3;
+    commit_elem!(group, notify(var));
+    assert_eq!(task.await.unwrap(), Some(3));
+
+    // Stream ends once the group is unregistered.
+    let mut values = group.watch_field(&group.var);
+    let task = tokio::spawn(async move { values.next().await });
+    drop(group);
+    assert_eq!(task.await.unwrap(), None);
+}
+
+#[test]
+fn watch_field_releases_waker() {
+    use futures::Stream;
+    use std::{pin::Pin, sync::Arc, task::Context};
+
+    struct Flag;
+    impl std::task::Wake for Flag {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let storage = config_it::create_storage();
+    let group = storage.create::<Foo>(["watch", "waker"]).unwrap().updated();
+    let flag = Arc::new(Flag);
+    let waker = flag.clone().into();
+
+    // Moving a pending stream doesn't leave its previous registration behind.
+    let mut values = Box::new(group.watch_field(&group.var));
+    assert!(Pin::new(&mut *values).poll_next(&mut Context::from_waker(&waker)).is_pending());
+    let mut values = *values;
+    assert!(Pin::new(&mut values).poll_next(&mut Context::from_waker(&waker)).is_pending());
+    assert_eq!(Arc::strong_count(&flag), 3);
+
+    drop(values);
+    assert_eq!(Arc::strong_count(&flag), 2);
+}
@@ -85,3 +85,200 @@ fn test(with_key: Option<impl AsRef<[u8]>>) {
     assert!(group.secret_floats.is_empty());
     assert!(group.secret_map.is_empty());
 }
+
+#[test]
+fn test_crypt_random_nonce() {
+    let storage = config_it::create_storage();
+    storage.set_crypt_key("nonce-test");
+
+    let mut group = storage.create::<CryptTest>(["Nonce"]).unwrap().updated();
+    group.secret_seq = vec![1, 2, 3];
+    group.secret_floats = vec![1., 2., 3.];
+    commit_elem!(group, notify(secret_seq, secret_floats));
+
+    let first = storage.exporter().collect();
+    let second = storage.exporter().collect();
+    let field = |archive: &config_it::Archive, name: &str| {
+        archive.find_path(["Nonce"]).unwrap().get_value(name).unwrap().as_str().unwrap().to_owned()
+    };
+
+    // Same value is never encrypted into the same ciphertext.
+    assert_ne!(field(&first, "secret_seq"), field(&second, "secret_seq"));
+}
+
+#[test]
+fn test_crypt_legacy_format() {
+    // Encrypted by the legacy format, which used a fixed nonce.
+    const LEGACY: &str = "+/+sE/cRE+t//7IUkHEUZ5bHHuNl8kxCFknLp4jG1dYyrNlMgZJFuNA";
+
+    let storage = config_it::create_storage();
+    storage.set_crypt_key("legacy-key");
+
+    let archive = serde_json::from_value(serde_json::json!({
+        "~Legacy": { "secret_str": LEGACY }
+    }))
+    .unwrap();
+    storage.import(archive);
+
+    let group = storage.create::<CryptTest>(["Legacy"]).unwrap().updated();
+    assert_eq!(group.secret_str, "legacy secret");
+
+    // Legacy value is re-encrypted in the new format on the next dump.
+    let exported = storage.exporter().collect();
+    let value = exported.find_path(["Legacy"]).unwrap().get_value("secret_str").unwrap();
+    assert!(!value.as_str().unwrap().starts_with("+/+sE/cRE+t//"));
+    drop(group);
+
+    let group = storage.create::<CryptTest>(["Legacy"]).unwrap().updated();
+    assert_eq!(group.secret_str, "legacy secret");
+}
+
+#[test]
+fn test_crypt_key_providers() {
+    use config_it::config::crypt::{CryptKeyError, EnvKey, FileKey, PassphraseKey, CRYPT_SALT_KEY};
+
+    fn roundtrip(
+        export_with: impl config_it::config::crypt::KeyProvider,
+        import_with: impl config_it::config::crypt::KeyProvider,
+    ) -> String {
+        let storage = config_it::create_storage();
+        storage.set_crypt_key_provider(export_with);
+
+        let mut group = storage.create::<CryptTest>(["Provider"]).unwrap().updated();
+        group.secret_str = "moved".to_string();
+        commit_elem!(group, notify(secret_str));
+        let exported = storage.exporter().collect();
+
+        let storage = config_it::create_storage();
+        storage.set_crypt_key_provider(import_with);
+        storage.import(exported);
+        storage.create::<CryptTest>(["Provider"]).unwrap().updated().secret_str.clone()
+    }
+
+    // Salt is exported along with the archive, thus the same passphrase works anywhere.
+    let passphrase = || PassphraseKey::new("correct horse").rounds(1000);
+    assert_eq!(roundtrip(passphrase(), passphrase()), "moved");
+    assert_ne!(roundtrip(passphrase(), PassphraseKey::new("wrong").rounds(1000)), "moved");
+
+    let storage = config_it::create_storage();
+    storage.set_crypt_key_provider(passphrase());
+    let group = storage.create::<CryptTest>(["Provider"]).unwrap();
+    drop(group);
+    assert!(storage.exporter().collect().get_value(CRYPT_SALT_KEY).is_some());
+
+    // 32 bytes key encoded in base64.
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+    std::env::set_var("CONFIG_IT_TEST_CRYPT_KEY", KEY);
+    let env = || EnvKey("CONFIG_IT_TEST_CRYPT_KEY".into());
+    assert_eq!(roundtrip(env(), env()), "moved");
+
+    let path = std::env::temp_dir().join(format!("config-it-key-{}", std::process::id()));
+    std::fs::write(&path, b"0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(roundtrip(FileKey(path.clone()), env()), "moved");
+    std::fs::remove_file(&path).unwrap();
+
+    let callback = || Ok::<_, CryptKeyError>(*b"0123456789abcdef0123456789abcdef");
+    assert_eq!(roundtrip(callback, env()), "moved");
+    assert_ne!(roundtrip(callback, EnvKey("CONFIG_IT_TEST_UNSET_KEY".into())), "moved");
+
+    // Decryption failures are reported by the import.
+    let storage = config_it::create_storage();
+    storage.set_crypt_key_provider(callback);
+    let mut group = storage.create::<CryptTest>(["Provider"]).unwrap().updated();
+    group.secret_str = "moved".to_string();
+    commit_elem!(group, notify(secret_str));
+    let exported = storage.exporter().collect();
+
+    let storage = config_it::create_storage();
+    storage.set_crypt_key_provider(PassphraseKey::new("wrong").rounds(1000));
+    let _group = storage.create::<CryptTest>(["Provider"]).unwrap();
+    let report = storage.import(exported).apply();
+    assert!(matches!(
+        report.find(["Provider"], "secret_str"),
+        Some(config_it::config::import::ItemOutcome::DecryptFailed(_))
+    ));
+}
+
+#[test]
+fn test_crypt_key_rotation() {
+    use config_it::config::crypt::StaticKey;
+
+    let old = || StaticKey::from_hashed("old");
+    let new = || StaticKey::from_hashed("new");
+
+    let storage = config_it::create_storage();
+    storage.set_crypt_key_provider(old());
+
+    let mut live = storage.create::<CryptTest>(["Live"]).unwrap().updated();
+    live.secret_str = "live".to_string();
+    commit_elem!(live, notify(secret_str));
+
+    let mut cached = storage.create::<CryptTest>(["Cached"]).unwrap().updated();
+    cached.secret_str = "cached".to_string();
+    commit_elem!(cached, notify(secret_str));
+    drop(cached);
+
+    // Value encrypted with unrelated key can't be rotated.
+    let foreign = {
+        let storage = config_it::create_storage();
+        storage.set_crypt_key("foreign");
+        let mut group = storage.create::<CryptTest>(["Foreign"]).unwrap().updated();
+        group.secret_str = "foreign".to_string();
+        commit_elem!(group, notify(secret_str));
+        drop(group);
+        storage.exporter().collect()
+    };
+    storage.import(foreign);
+
+    let report = storage.rotate_crypt_key(old(), new()).unwrap();
+    assert!(report.failed.iter().all(|x| x.path == ["Foreign"] && x.layer.is_none()));
+    assert_eq!(report.failed.len(), 4);
+
+    drop(live);
+    let exported = storage.exporter().collect();
+
+    let storage = config_it::create_storage();
+    storage.set_crypt_key_provider(new());
+    storage.import(exported);
+
+    assert_eq!(storage.create::<CryptTest>(["Live"]).unwrap().updated().secret_str, "live");
+    assert_eq!(storage.create::<CryptTest>(["Cached"]).unwrap().updated().secret_str, "cached");
+}
+
+#[test]
+fn test_crypt_foreign_salt() {
+    use config_it::config::crypt::{PassphraseKey, CRYPT_SALT_KEY};
+
+    let passphrase = || PassphraseKey::new("correct horse").rounds(1000);
+    let export = |name: &str| {
+        let storage = config_it::create_storage();
+        storage.set_crypt_key_provider(passphrase());
+        let mut group = storage.create::<CryptTest>([name]).unwrap().updated();
+        group.secret_str = name.to_string();
+        commit_elem!(group, notify(secret_str));
+        drop(group);
+        storage.exporter().collect()
+    };
+
+    let storage = config_it::create_storage();
+    storage.set_crypt_key_provider(passphrase());
+    storage.import(export("Local"));
+    let salt = storage.exporter().collect().get_value(CRYPT_SALT_KEY).cloned();
+
+    // Archive of another salt doesn't replace the one of the storage, and its values are
+    // re-encrypted, thus both secrets remain readable.
+    storage.import(export("Foreign"));
+    storage.set_layer(config_it::Layer::Override, export("Layered"));
+
+    let exported = storage.exporter().collect();
+    assert_eq!(exported.get_value(CRYPT_SALT_KEY).cloned(), salt);
+
+    for name in ["Local", "Foreign", "Layered"] {
+        assert_eq!(storage.create::<CryptTest>([name]).unwrap().updated().secret_str, name);
+    }
+
+    let storage = config_it::create_storage();
+    storage.set_crypt_key_provider(passphrase());
+    storage.import(exported);
+    assert_eq!(storage.create::<CryptTest>(["Foreign"]).unwrap().updated().secret_str, "Foreign");
+}
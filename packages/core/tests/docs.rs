@@ -0,0 +1,48 @@
+#![cfg(feature = "config-derive")]
+
+use config_it::config::docs::ReferenceDoc;
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    /// Port to listen on.
+    ///
+    /// Ports below 1024 require <root> privileges.
+    #[config(default = 80, env = "SERVER_PORT", previous_names = ["listen_port"])]
+    port: u16,
+
+    #[config(default = "", secret)]
+    token: String,
+
+    #[config(default = 1, deprecated = "use `threads` instead")]
+    workers: u32,
+}
+
+#[test]
+fn reference_markdown() {
+    let storage = config_it::create_storage();
+    let _b = storage.create::<Server>(["b"]).unwrap();
+    let _a = storage.create::<Server>(["a", "nested"]).unwrap();
+
+    let markdown = ReferenceDoc::from_storage(&storage).to_markdown();
+    assert!(markdown.starts_with("# Configuration Reference\n"));
+    assert!(markdown.find("## `a.nested`").unwrap() < markdown.find("## `b`").unwrap());
+
+    let expected = "### `port`\n\nPort to listen on.\n\nPorts below 1024 require <root> privileges.\n\n\
+        - Type: `u16`\n- Default: `80`\n- Environment: `SERVER_PORT`\n- Previous names: `listen_port`\n";
+    assert!(markdown.contains(expected), "{markdown}");
+    assert!(markdown.contains("- Default: (secret)\n- Flags: writeonly, secret\n"));
+    assert!(markdown.contains("- Deprecated: use `threads` instead\n"));
+}
+
+#[test]
+fn reference_html() {
+    let mut doc = ReferenceDoc::new();
+    doc.add_template::<Server>(["server"]);
+
+    let html = doc.to_html();
+    assert!(html.contains("<h2><code>server</code></h2>"));
+    assert!(html.contains("<p>Ports below 1024 require &lt;root&gt; privileges.</p>"));
+    assert!(html.contains("<li>Default: <code>80</code></li>"));
+    assert!(html.contains("<li>Deprecated: use `threads` instead</li>"));
+    assert!(html.ends_with("</html>\n"));
+}
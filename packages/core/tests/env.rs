@@ -0,0 +1,60 @@
+#![cfg(feature = "config-derive")]
+
+use config_it::{
+    config::{change::ChangeNotice, env::var_name, storage::UpdateOrigin},
+    Layer,
+};
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    #[config(default = 80, max = 9000)]
+    port: u16,
+
+    #[config(default = "localhost")]
+    host: String,
+
+    #[config(default = 1)]
+    workers: u32,
+}
+
+#[test]
+fn env_overlay() {
+    assert_eq!(
+        var_name("CFG_TEST", ["net", "my-server"], "port"),
+        "CFG_TEST__NET__MY_SERVER__PORT"
+    );
+
+    std::env::set_var("CFG_TEST__NET__SERVER__PORT", "8080");
+    std::env::set_var("CFG_TEST__NET__SERVER__HOST", "example.com");
+    std::env::set_var("CFG_TEST__NET__SERVER__WORKERS", "many");
+
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["net", "server"]).unwrap();
+    storage.set_env_prefix("CFG_TEST");
+
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str(), server.workers), (8080, "example.com", 1));
+    assert_eq!(storage.source_layer(["net", "server"], "port"), Layer::Environment);
+
+    // Values are validated like imported ones, and removed variables fall back to defaults.
+    std::env::set_var("CFG_TEST__NET__SERVER__PORT", "10000");
+    std::env::remove_var("CFG_TEST__NET__SERVER__HOST");
+    let mut changes = storage.subscribe_changes();
+    storage.reload_env();
+    let Ok(ChangeNotice::Changed(event)) = changes.try_recv() else { panic!() };
+    assert_eq!(event.origin, UpdateOrigin::Environment);
+
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (9000, "localhost"));
+
+    // Groups created later read their variables on creation.
+    std::env::set_var("CFG_TEST__LATE__WORKERS", "4");
+    let late = storage.create::<Server>(["late"]).unwrap().updated();
+    assert_eq!(late.workers, 4);
+    assert_eq!(storage.source_layer(["late"], "workers"), Layer::Environment);
+
+    // Variables are never written into exported archives.
+    let exported = storage.exporter().collect();
+    assert_eq!(exported.find_path(["late"]).unwrap().get_value("workers"), None);
+    assert_eq!(exported.find_path(["net", "server"]).unwrap().get_value("port"), None);
+}
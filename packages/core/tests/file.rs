@@ -0,0 +1,207 @@
+#![cfg(feature = "config-derive")]
+
+use std::time::Duration;
+
+use config_it::config::file::{FileBackend, FileBackendOptions};
+
+#[derive(config_it::Template, Clone)]
+struct Window {
+    #[config(default = 800)]
+    width: u32,
+
+    #[config(default = "main")]
+    title: String,
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("config-it-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn file_backend_roundtrip() {
+    let path = temp_path("roundtrip.json");
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let storage = config_it::create_storage();
+        let options = FileBackendOptions::default().debounce(Duration::from_millis(10));
+        let backend = FileBackend::attach_with(&storage, &path, options).unwrap();
+        assert!(!path.exists());
+
+        let mut window = storage.create::<Window>(["window"]).unwrap();
+        window.width = 1024;
+        window.commit_elem(&window.width, true);
+
+        // Debounced save is performed in background.
+        for _ in 0..200 {
+            if !backend.is_dirty() && path.exists() {
+                break;
+            }
+
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["~window"]["width"], 1024);
+
+        // Pending changes are saved on drop.
+        window.title = "changed".into();
+        window.commit_elem(&window.title, true);
+    }
+
+    let storage = config_it::create_storage();
+    let backend = FileBackend::attach(&storage, &path).unwrap();
+    let window = storage.create::<Window>(["window"]).unwrap().updated();
+    assert_eq!(window.width, 1024);
+    assert_eq!(window.title, "changed");
+
+    drop(backend);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn file_backend_rejects_broken_file() {
+    let path = temp_path("broken.json");
+    std::fs::write(&path, "{ not a json").unwrap();
+
+    let storage = config_it::create_storage();
+    assert!(FileBackend::attach(&storage, &path).is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "{ not a json");
+
+    assert!(FileBackend::attach(&storage, temp_path("unknown.format")).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn file_watcher_reloads_changes() {
+    use config_it::config::watch::{FileWatcher, FileWatcherOptions};
+    use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc};
+
+    let base = temp_path("watch-base.json");
+    let local = temp_path("watch-local.json");
+    std::fs::write(&base, r#"{ "~window": { "width": 640, "title": "base" } }"#).unwrap();
+    let _ = std::fs::remove_file(&local);
+
+    let storage = config_it::create_storage();
+    let mut window = storage.create::<Window>(["window"]).unwrap();
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let options = FileWatcherOptions::default().poll_interval(Duration::from_secs(3600)).on_error(
+        Arc::new({
+            let errors = errors.clone();
+            move |_: &_| {
+                errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }),
+    );
+
+    let watcher = FileWatcher::watch_with(&storage, [&base, &local], options).unwrap();
+    assert!(window.update());
+    assert_eq!((window.width, window.title.as_str()), (640, "base"));
+    assert!(window.consume_update(&window.width) && window.consume_update(&window.title));
+
+    // Later file takes precedence; only changed values are applied.
+    std::fs::write(&local, r#"{ "~window": { "title": "local" } }"#).unwrap();
+    assert!(watcher.check_now());
+    assert!(window.update());
+    assert!(!window.consume_update(&window.width));
+    assert!(window.consume_update(&window.title));
+    assert_eq!(window.title, "local");
+
+    // Broken content is reported, and previous values are retained.
+    std::fs::write(&base, r#"{ "~window": { "width": "#).unwrap();
+    assert!(!watcher.check_now());
+    assert_eq!(errors.load(Ordering::Relaxed), 1);
+    assert!(!window.update());
+
+    std::fs::write(&base, r#"{ "~window": { "width": 1280 } }"#).unwrap();
+    assert!(watcher.check_now());
+    assert!(window.update());
+    assert_eq!((window.width, window.title.as_str()), (1280, "local"));
+
+    // Removed values fall back to the template default.
+    std::fs::write(&local, "{}").unwrap();
+    assert!(watcher.check_now());
+    assert!(window.update());
+    assert_eq!((window.width, window.title.as_str()), (1280, "main"));
+
+    // Writes from the file backend are not reloaded.
+    let backend = FileBackend::attach(&storage, &local).unwrap();
+    window.width = 1920;
+    window.commit_elem(&window.width, true);
+    backend.flush().unwrap();
+    assert!(!watcher.check_now());
+
+    drop((watcher, backend));
+    std::fs::remove_file(&base).unwrap();
+    std::fs::remove_file(&local).unwrap();
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn file_backend_keeps_comments() {
+    #[derive(config_it::Template, Clone)]
+    struct Server {
+        #[config(default = 80)]
+        port: u16,
+
+        /// Seconds before idle connections are closed.
+        #[config(default = 30)]
+        timeout: u32,
+    }
+
+    let path = temp_path("comments.toml");
+    std::fs::write(&path, "# Hand-written\n[server]\nport = 8080 # HTTP\n").unwrap();
+
+    let storage = config_it::create_storage();
+    let backend = FileBackend::attach(&storage, &path).unwrap();
+    let mut server = storage.create::<Server>(["server"]).unwrap().updated();
+    assert_eq!(server.port, 8080);
+
+    server.port = 9090;
+    server.commit_elem(&server.port, true);
+    backend.flush().unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        text,
+        "# Hand-written\n[server]\nport = 9090 # HTTP\n\
+         # Seconds before idle connections are closed.\ntimeout = 30\n"
+    );
+
+    drop(backend);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn file_watcher_ignores_self_writes_through_symlink() {
+    use config_it::config::watch::{FileWatcher, FileWatcherOptions};
+
+    let dir = temp_path("symlink-real");
+    let link = temp_path("symlink-link");
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_file(&link);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::os::unix::fs::symlink(&dir, &link).unwrap();
+
+    let storage = config_it::create_storage();
+    let mut window = storage.create::<Window>(["window"]).unwrap();
+
+    // The file doesn't exist yet when the backend registers its first write.
+    let options = FileWatcherOptions::default().poll_interval(Duration::from_secs(3600));
+    let watcher = FileWatcher::watch_with(&storage, [dir.join("window.json")], options).unwrap();
+    let backend = FileBackend::attach(&storage, link.join("window.json")).unwrap();
+
+    window.width = 1920;
+    window.commit_elem(&window.width, true);
+    backend.flush().unwrap();
+    assert!(!watcher.check_now());
+
+    drop((watcher, backend));
+    std::fs::remove_file(&link).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "config-derive")]
+
+use config_it::{
+    config::import::{ItemOutcome, UnknownKey},
+    Archive, ArchivePatch,
+};
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    #[config(default = 80)]
+    port: u16,
+
+    #[config(default = "localhost")]
+    host: String,
+}
+
+#[derive(config_it::Template, Clone)]
+struct Limits {
+    #[config(default = 3, min = 1, max = 5)]
+    retries: i32,
+
+    #[config(one_of = [1, 2, 3])]
+    level: i32,
+
+    #[config(default = 10)]
+    timeout: u32,
+
+    #[config(no_import)]
+    pinned: i32,
+}
+
+fn archive(json: &str) -> Archive {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn import_removals() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["server"]).unwrap();
+
+    storage.import(archive(r#"{ "~server": { "port": 8080, "host": "remote" } }"#));
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (8080, "remote"));
+
+    // Merging import keeps values which are missing from the imported archive.
+    storage.import(archive(r#"{ "~server": { "port": 8081 } }"#));
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (8081, "remote"));
+    assert!(server.consume_update(&server.port));
+
+    // Tombstones reset the items to their template defaults.
+    let mut patch = ArchivePatch::default();
+    patch.find_or_create_path_mut(["server"]).tombstone_value("host");
+    storage.import_patch(patch);
+    assert!(server.update());
+    assert!(!server.consume_update(&server.port));
+    assert!(server.consume_update(&server.host));
+    assert_eq!((server.port, server.host.as_str()), (8081, "localhost"));
+
+    // Replacing import resets every item missing from the imported archive.
+    storage.import(archive(r#"{ "~server": { "host": "other" } }"#)).merge_onto_cache(false);
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (80, "other"));
+
+    // Removed categories reset all of their items.
+    storage.import(Archive::default()).merge_onto_cache(false);
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (80, "localhost"));
+
+    let later = storage.create::<Server>(["server", "later"]).unwrap().updated();
+    assert_eq!(later.port, 80);
+}
+
+#[test]
+fn import_report() {
+    let storage = config_it::create_storage();
+    let mut limits = storage.create::<Limits>(["limits"]).unwrap();
+
+    let report = storage
+        .import(archive(
+            r#"{ "~limits": {
+                "retries": 9, "level": 7, "timeout": "long", "pinned": 1, "typo": 0
+            }, "~later": { "retries": 1 } }"#,
+        ))
+        .apply();
+
+    let outcome = |name| report.find(["limits"], name).cloned();
+    assert_eq!(outcome("retries"), Some(ItemOutcome::Clamped));
+    assert!(matches!(outcome("level"), Some(ItemOutcome::Rejected(_))));
+    assert!(matches!(outcome("timeout"), Some(ItemOutcome::Rejected(x)) if x.contains("string")));
+    assert_eq!(outcome("pinned"), Some(ItemOutcome::Skipped));
+    assert_eq!(report.unknown.len(), 2);
+    assert!(report
+        .unknown
+        .contains(&UnknownKey { path: vec!["limits".into()], name: "typo".into() }));
+    assert!(report
+        .unknown
+        .contains(&UnknownKey { path: vec!["later".into()], name: "retries".into() }));
+    assert!(!report.is_clean());
+
+    assert!(limits.update());
+    assert_eq!((limits.retries, limits.level, limits.timeout, limits.pinned), (5, 0, 10, 0));
+
+    // Values of categories without a live group are loaded once the group is created.
+    assert_eq!(storage.create::<Limits>(["later"]).unwrap().updated().retries, 1);
+
+    // Unchanged values are not loaded again.
+    let report = storage
+        .import(archive(r#"{ "~limits": { "retries": 9, "timeout": 20 } }"#))
+        .merge_onto_cache(false)
+        .apply();
+
+    assert_eq!(report.find(["limits"], "retries"), None);
+    assert_eq!(report.find(["limits"], "timeout"), Some(&ItemOutcome::Applied));
+    assert_eq!(report.find(["limits"], "level"), Some(&ItemOutcome::Reset));
+    assert!(report.is_clean());
+}
+
+#[test]
+fn import_strict() {
+    let storage = config_it::create_storage();
+    let mut limits = storage.create::<Limits>(["limits"]).unwrap().updated();
+    let mut server = storage.create::<Server>(["server"]).unwrap().updated();
+
+    // Single invalid value aborts the whole import.
+    let report = storage
+        .import(archive(
+            r#"{ "~limits": { "retries": 2, "level": 7 }, "~server": { "port": 8080 } }"#,
+        ))
+        .strict(true)
+        .apply();
+
+    assert!(report.aborted);
+    assert_eq!(report.find(["limits"], "retries"), Some(&ItemOutcome::Applied));
+    assert!(matches!(report.find(["limits"], "level"), Some(ItemOutcome::Rejected(_))));
+    assert!(!limits.update());
+    assert!(!server.update());
+
+    // So does an unknown key.
+    let report = storage
+        .import(archive(r#"{ "~server": { "port": 8080, "prot": 1 } }"#))
+        .strict(true)
+        .apply();
+    assert!(report.aborted);
+    assert!(!server.update());
+
+    // Or a category which matches no group.
+    let report = storage
+        .import(archive(r#"{ "~server": { "port": 8080 }, "~sever": { "port": 1 } }"#))
+        .strict(true)
+        .apply();
+    assert!(report.aborted);
+    assert_eq!(report.unknown, [UnknownKey { path: vec!["sever".into()], name: "port".into() }]);
+    assert!(!server.update());
+
+    let report = storage
+        .import(archive(r#"{ "~limits": { "retries": 9 }, "~server": { "port": 8080 } }"#))
+        .strict(true)
+        .apply();
+
+    assert!(!report.aborted);
+    assert_eq!(report.find(["limits"], "retries"), Some(&ItemOutcome::Clamped));
+    assert!(limits.update() && server.update());
+    assert_eq!((limits.retries, server.port), (5, 8080));
+}
@@ -0,0 +1,126 @@
+#![cfg(feature = "config-derive")]
+
+use config_it::{Archive, Layer};
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    #[config(default = 80)]
+    port: u16,
+
+    #[config(default = "localhost")]
+    host: String,
+}
+
+fn archive(json: &str) -> Archive {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn layer_precedence() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["server"]).unwrap();
+
+    storage.set_layer(Layer::User, archive(r#"{ "~server": { "port": 8080 } }"#));
+    storage.set_layer(Layer::System, archive(r#"{ "~server": { "port": 1, "host": "sys" } }"#));
+
+    assert!(server.update());
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.host, "sys");
+    assert_eq!(storage.source_layer(["server"], "port"), Layer::User);
+    assert_eq!(storage.source_layer(["server"], "host"), Layer::System);
+
+    // Clearing an override restores the value below.
+    storage.set_layer(Layer::Override, archive(r#"{ "~server": { "port": 9090 } }"#));
+    assert!(server.update());
+    assert_eq!(server.port, 9090);
+    assert_eq!(storage.source_layer(["server"], "port"), Layer::Override);
+
+    storage.clear_layer(Layer::Override);
+    assert!(server.update());
+    assert_eq!(server.port, 8080);
+    assert!(storage.layer(Layer::Override).is_none());
+
+    // Items which are no longer supplied by any layer fall back to the template default.
+    storage.clear_layer(Layer::System);
+    storage.clear_layer(Layer::User);
+    assert!(server.update());
+    assert_eq!(server.port, 80);
+    assert_eq!(server.host, "localhost");
+    assert_eq!(storage.source_layer(["server"], "port"), Layer::Defaults);
+}
+
+#[test]
+fn layer_applies_to_later_groups() {
+    let storage = config_it::create_storage();
+    storage.set_layer(Layer::Environment, archive(r#"{ "~server": { "host": "env" } }"#));
+
+    let server = storage.create::<Server>(["server"]).unwrap().updated();
+    assert_eq!(server.host, "env");
+    assert_eq!(server.port, 80);
+
+    // Setting an identical layer doesn't trigger any update.
+    let mut server = server;
+    storage.set_layer(Layer::CommandLine, archive(r#"{ "~server": { "host": "env" } }"#));
+    assert!(!server.update());
+}
+
+#[test]
+fn transient_layers_are_not_exported() {
+    let storage = config_it::create_storage();
+    storage.import(archive(r#"{ "~server": { "port": 8080 } }"#));
+    let mut server = storage.create::<Server>(["server"]).unwrap().updated();
+
+    let overrides = r#"{ "~server": { "port": 9090, "host": "override" } }"#;
+    storage.set_layer(Layer::Override, archive(overrides));
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (9090, "override"));
+
+    // Items supplied by transient layers are exported with their underlying values.
+    let exported = storage.exporter().collect();
+    let node = exported.find_path(["server"]).unwrap();
+    assert_eq!(node.get_value("port"), Some(&8080.into()));
+    assert_eq!(node.get_value("host"), None);
+
+    // Same for groups dumped into the cache on disposal, which still observe the layer on
+    // recreation.
+    drop(server);
+    let exported = storage.exporter().collect();
+    assert_eq!(exported.find_path(["server"]).unwrap().get_value("port"), Some(&8080.into()));
+
+    let mut server = storage.create::<Server>(["server"]).unwrap().updated();
+    assert_eq!((server.port, server.host.as_str()), (9090, "override"));
+
+    storage.clear_layer(Layer::Override);
+    assert!(server.update());
+    assert_eq!(server.host, "localhost");
+}
+
+#[test]
+fn transient_layers_win_over_imports() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["server"]).unwrap().updated();
+
+    storage.set_layer(Layer::User, archive(r#"{ "~server": { "host": "user" } }"#));
+    storage.set_layer(Layer::Override, archive(r#"{ "~server": { "port": 9090 } }"#));
+    assert!(server.update());
+
+    // Importing a file which sets the same key doesn't touch the pinned value.
+    storage.import(archive(r#"{ "~server": { "port": 8080, "host": "file" } }"#));
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (9090, "file"));
+
+    // Removed values fall back to the layers below, rather than to the template defaults.
+    let mut patch = config_it::ArchivePatch::default();
+    patch.find_or_create_path_mut(["server"]).tombstone_value("port");
+    patch.find_or_create_path_mut(["server"]).tombstone_value("host");
+    storage.import_patch(patch);
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (9090, "user"));
+
+    // The imported value takes effect once the override is cleared.
+    storage.import(archive(r#"{ "~server": { "port": 8080 } }"#));
+    assert!(!server.update());
+    storage.clear_layer(Layer::Override);
+    assert!(server.update());
+    assert_eq!(server.port, 8080);
+}
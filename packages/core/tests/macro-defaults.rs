@@ -1,5 +1,6 @@
 #![cfg(feature = "config-derive")]
 
+#[allow(dead_code)]
 #[derive(config_it::Template, Clone)]
 struct Foo {
     /// This is to
@@ -188,3 +188,91 @@ fn config_set_valid_operations() {
 
     block_on(async_op);
 }
+
+#[test]
+fn deprecated_field() {
+    #[derive(Clone, config_it::Template)]
+    struct Legacy {
+        #[config(default = 1, deprecated = "use `threads` instead")]
+        workers: u32,
+
+        #[config(default = 4)]
+        threads: u32,
+    }
+
+    let storage = config_it::create_storage();
+    let group = storage.create::<Legacy>(["legacy"]).unwrap();
+
+    assert_eq!(group.meta(&group.workers).deprecated, Some("use `threads` instead"));
+    assert_eq!(group.meta(&group.threads).deprecated, None);
+
+    #[cfg(feature = "jsonschema")]
+    {
+        let schema = group.meta(&group.workers).schema.as_ref().unwrap();
+        assert!(schema.schema.metadata.as_ref().unwrap().deprecated);
+    }
+
+    // Deprecated items are still imported.
+    let mut group = group.updated();
+    storage.import(serde_json::from_str(r#"{ "~legacy": { "workers": 2 } }"#).unwrap());
+    assert!(group.update());
+    assert_eq!(group.workers, 2);
+}
+
+#[test]
+fn deprecated_field_warns_on_non_default() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Counts the warnings about deprecated items.
+    struct Warnings(Arc<AtomicUsize>);
+
+    impl tr::Subscriber for Warnings {
+        fn enabled(&self, _: &tr::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &tr::span::Attributes<'_>) -> tr::span::Id {
+            tr::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &tr::span::Id, _: &tr::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tr::span::Id, _: &tr::span::Id) {}
+
+        fn event(&self, event: &tr::Event<'_>) {
+            if event.metadata().fields().field("note").is_some() {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        fn enter(&self, _: &tr::span::Id) {}
+
+        fn exit(&self, _: &tr::span::Id) {}
+    }
+
+    #[derive(Clone, config_it::Template)]
+    struct Legacy {
+        #[config(default = 1, deprecated = "use `threads` instead")]
+        workers: u32,
+    }
+
+    let warnings = Arc::new(AtomicUsize::new(0));
+    tr::subscriber::with_default(Warnings(warnings.clone()), || {
+        let storage = config_it::create_storage();
+        let group = storage.create::<Legacy>(["legacy"]).unwrap();
+
+        // Exported default value doesn't warn on reload.
+        let exported = storage.exporter().collect();
+        storage.import(exported).apply_as_patch(false);
+        drop(group);
+        let group = storage.create::<Legacy>(["legacy"]).unwrap();
+        assert_eq!(warnings.load(Ordering::Relaxed), 0);
+
+        storage.import(serde_json::from_str(r#"{ "~legacy": { "workers": 2 } }"#).unwrap());
+        assert_eq!(warnings.load(Ordering::Relaxed), 1);
+        drop(group);
+    });
+}
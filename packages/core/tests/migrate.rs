@@ -0,0 +1,77 @@
+#![cfg(feature = "config-derive")]
+
+use config_it::{config::migrate::SCHEMA_VERSION_KEY, Archive};
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    #[config(default = 80, previous_names = ["listen_port"])]
+    port: u16,
+
+    #[config(default = "localhost")]
+    host: String,
+}
+
+fn archive(json: &str) -> Archive {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn migrate_archive() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["server"]).unwrap().updated();
+
+    // Migrations run in order of version, regardless of registration order.
+    storage.add_group_migration(2, ["server"], |node| {
+        if let Some(addr) = node.remove_value("addr") {
+            node.insert_value("host", addr);
+        }
+    });
+    storage.add_migration(1, |archive| {
+        if let Some(node) = archive.remove_path("srv") {
+            archive.insert_path("server", node);
+        }
+    });
+    assert_eq!(storage.schema_version(), 2);
+
+    let report =
+        storage.import(archive(r#"{ "~srv": { "listen_port": 8080, "addr": "remote" } }"#)).apply();
+    assert!(report.is_clean());
+    assert!(server.update());
+    assert_eq!((server.port, server.host.as_str()), (8080, "remote"));
+
+    // Only the current names are exported.
+    let exported = storage.exporter().collect();
+    assert_eq!(exported.get_value(SCHEMA_VERSION_KEY), Some(&2.into()));
+
+    let node = exported.find_path(["server"]).unwrap();
+    assert_eq!(node.get_value("port"), Some(&8080.into()));
+    assert_eq!(node.get_value("listen_port"), None);
+
+    // Archives of the current version are not migrated.
+    let report = storage
+        .import(archive(r#"{ "$schema-version": 2, "~server": { "addr": "other" } }"#))
+        .apply();
+    assert_eq!(report.unknown.len(), 1);
+    assert!(!server.update());
+}
+
+#[test]
+fn previous_names_of_absent_groups() {
+    let storage = config_it::create_storage();
+    storage.import(archive(r#"{ "~server": { "listen_port": 8080 } }"#));
+
+    // Without a live group, the legacy key is kept as imported.
+    let exported = storage.exporter().collect();
+    let node = exported.find_path(["server"]).unwrap();
+    assert_eq!(node.get_value("listen_port"), Some(&8080.into()));
+    assert_eq!(node.get_value("port"), None);
+
+    // Groups created later still read it, and it's superseded by the current name from then on.
+    let server = storage.create::<Server>(["server"]).unwrap().updated();
+    assert_eq!(server.port, 8080);
+
+    let exported = storage.exporter().collect();
+    let node = exported.find_path(["server"]).unwrap();
+    assert_eq!(node.get_value("port"), Some(&8080.into()));
+    assert_eq!(node.get_value("listen_port"), None);
+}
@@ -0,0 +1,59 @@
+#![cfg(all(feature = "config-derive", feature = "jsonschema"))]
+
+use config_it::config::schema::ArchiveSchema;
+use serde_json::json;
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    /// Port to listen on.
+    #[config(default = 80, min = 1, max = 9000)]
+    port: u16,
+
+    #[config(default = "http", one_of = ["http", "https"])]
+    proto: String,
+
+    #[config(default = "", secret)]
+    token: String,
+
+    #[config(default = 0, transient)]
+    sessions: u32,
+}
+
+#[test]
+fn storage_schema() {
+    let storage = config_it::create_storage();
+    let _server = storage.create::<Server>(["app", "server"]).unwrap();
+
+    let schema = serde_json::to_value(storage.archive_schema()).unwrap();
+    let server = &schema["properties"]["~app"]["properties"]["~server"];
+    assert_eq!(server["type"], "object");
+
+    let port = &server["properties"]["port"];
+    assert_eq!(port["description"], "Port to listen on.");
+    assert_eq!(port["default"], 80);
+    assert_eq!((port["minimum"].as_f64(), port["maximum"].as_f64()), (Some(1.), Some(9000.)));
+
+    let proto = &server["properties"]["proto"];
+    assert_eq!(proto["enum"], json!(["http", "https"]));
+    assert_eq!(proto["default"], "http");
+
+    // Secrets accept encrypted strings, and don't expose the default.
+    let token = &server["properties"]["token"];
+    assert!(token.get("default").is_none());
+    assert_eq!(token["anyOf"][1], json!({ "type": "string" }));
+
+    // Items which are never imported are omitted.
+    assert!(server["properties"].get("sessions").is_none());
+}
+
+#[test]
+fn template_schema() {
+    let mut schema = ArchiveSchema::new();
+    schema
+        .add_template::<Server>(["server"])
+        .category_rule(config_it::ArchiveCategoryRule::Wrap("[", "]"));
+
+    let schema = serde_json::to_value(schema.build()).unwrap();
+    assert!(schema["$schema"].is_string());
+    assert_eq!(schema["properties"]["[server]"]["properties"]["port"]["default"], 80);
+}
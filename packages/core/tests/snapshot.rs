@@ -0,0 +1,83 @@
+#![cfg(feature = "config-derive")]
+
+use config_it::{Archive, Layer};
+
+#[derive(config_it::Template, Clone)]
+struct Server {
+    #[config(default = 80)]
+    port: u16,
+
+    #[config(default = "localhost")]
+    host: String,
+}
+
+fn archive(json: &str) -> Archive {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn snapshot_restore() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["server"]).unwrap().updated();
+    let mut idle = storage.create::<Server>(["idle"]).unwrap().updated();
+
+    server.transaction(|x| x.port = 8080).unwrap();
+    storage.import(archive(r#"{ "~cached": { "port": 1 } }"#));
+    assert!(server.update());
+
+    let snapshot = storage.snapshot();
+    assert!(snapshot.archive().find_path(["cached"]).is_some());
+
+    server.transaction(|x| x.host = "broken".into()).unwrap();
+    storage.import(archive(r#"{ "~cached": { "port": 2 } }"#));
+    let mut later = storage.create::<Server>(["later"]).unwrap();
+    later.transaction(|x| x.port = 3).unwrap();
+    assert!(server.update());
+    assert!(server.consume_update(&server.port) && server.consume_update(&server.host));
+
+    storage.restore(&snapshot);
+
+    assert!(server.update());
+    assert!(!server.consume_update(&server.port));
+    assert!(server.consume_update(&server.host));
+    assert_eq!((server.port, server.host.as_str()), (8080, "localhost"));
+
+    // Unchanged groups are not notified.
+    assert!(!idle.update());
+
+    // Group created after the snapshot is restored to its defaults.
+    assert!(later.update());
+    assert_eq!(later.port, 80);
+
+    let cached = storage.create::<Server>(["cached"]).unwrap().updated();
+    assert_eq!(cached.port, 1);
+
+    // Snapshot can be restored repeatedly.
+    server.transaction(|x| x.port = 9090).unwrap();
+    storage.restore(&snapshot.clone());
+    assert!(server.update());
+    assert_eq!(server.port, 8080);
+}
+
+#[test]
+fn snapshot_restores_layers() {
+    let storage = config_it::create_storage();
+    let mut server = storage.create::<Server>(["server"]).unwrap().updated();
+
+    storage.set_layer(Layer::Override, archive(r#"{ "~server": { "port": 9090 } }"#));
+    let snapshot = storage.snapshot();
+
+    storage.clear_layer(Layer::Override);
+    assert!(server.update());
+    assert_eq!(server.port, 80);
+
+    storage.restore(&snapshot);
+    assert!(server.update());
+    assert_eq!(server.port, 9090);
+    assert_eq!(storage.source_layer(["server"], "port"), Layer::Override);
+
+    // Later layer edits are diffed against the restored layers.
+    storage.clear_layer(Layer::Override);
+    assert!(server.update());
+    assert_eq!(server.port, 80);
+}
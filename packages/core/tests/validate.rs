@@ -0,0 +1,181 @@
+#![cfg(feature = "config-derive")]
+
+use std::borrow::Cow;
+
+use config_it::{config::import::ItemOutcome, Archive, ArchivePatch, Validation};
+
+#[derive(config_it::Template, Clone)]
+#[config(validate = "Pool::validate")]
+struct Pool {
+    #[config(default = 1)]
+    min_conns: u32,
+
+    #[config(default = 10)]
+    max_conns: u32,
+
+    #[config(default = 5)]
+    idle: u32,
+}
+
+impl Pool {
+    /// Rejects inverted bounds, and clamps idle connections into them.
+    fn validate(&mut self) -> Result<Validation, Cow<'static, str>> {
+        if self.min_conns > self.max_conns {
+            return Err("min_conns exceeds max_conns".into());
+        }
+
+        let idle = self.idle.clamp(self.min_conns, self.max_conns);
+        if idle == self.idle {
+            return Ok(Validation::Valid);
+        }
+
+        self.idle = idle;
+        Ok(Validation::Modified)
+    }
+}
+
+fn archive(json: &str) -> Archive {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn validate_commit() {
+    let storage = config_it::create_storage();
+    let mut pool = storage.create::<Pool>(["pool"]).unwrap().updated();
+
+    pool.min_conns = 20;
+    assert!(pool.try_commit_elem(&pool.min_conns, true).is_err());
+    assert!(!pool.update());
+
+    let exported = storage.exporter().collect();
+    let exported = exported.find_path(["pool"]).unwrap().get_value("min_conns");
+    assert_eq!(exported, Some(&1.into()));
+
+    // Rejected value remains only in the local copy.
+    pool.min_conns = 1;
+
+    // Adjustment of other fields is committed together.
+    pool.max_conns = 3;
+    assert_eq!(pool.try_commit_elem(&pool.max_conns, true), Ok(Validation::Modified));
+    assert!(pool.update());
+    assert!(pool.consume_update(&pool.idle));
+    assert_eq!((pool.min_conns, pool.max_conns, pool.idle), (1, 3, 3));
+}
+
+#[test]
+fn validate_import() {
+    let storage = config_it::create_storage();
+    let mut pool = storage.create::<Pool>(["pool"]).unwrap().updated();
+
+    let report = storage.import(archive(r#"{ "~pool": { "min_conns": 12 } }"#)).apply();
+    assert!(matches!(
+        report.find(["pool"], "min_conns"),
+        Some(ItemOutcome::Rejected(x)) if x.contains("min_conns exceeds max_conns")
+    ));
+    assert!(!pool.update());
+
+    // Valid as a whole, though each value alone would be rejected or adjusted.
+    let report =
+        storage.import(archive(r#"{ "~pool": { "min_conns": 8, "max_conns": 20 } }"#)).apply();
+    assert_eq!(report.find(["pool"], "min_conns"), Some(&ItemOutcome::Applied));
+    assert_eq!(report.find(["pool"], "idle"), Some(&ItemOutcome::Clamped));
+    assert!(report.is_clean());
+
+    assert!(pool.update());
+    assert_eq!((pool.min_conns, pool.max_conns, pool.idle), (8, 20, 8));
+
+    // Strict import is aborted by the template validator as well.
+    let report = storage.import(archive(r#"{ "~pool": { "max_conns": 4 } }"#)).strict(true).apply();
+    assert!(report.aborted);
+    assert!(!pool.update());
+}
+
+#[test]
+fn validate_reset() {
+    let storage = config_it::create_storage();
+    let mut pool = storage.create::<Pool>(["pool"]).unwrap().updated();
+
+    storage.import(archive(r#"{ "~pool": { "min_conns": 15, "max_conns": 20 } }"#));
+    assert!(pool.update());
+
+    // Resetting `max_conns` to its default would put it below `min_conns`.
+    let mut patch = ArchivePatch::default();
+    patch.find_or_create_path_mut(["pool"]).tombstone_value("max_conns");
+    let report = storage.import_patch(patch).apply();
+    assert!(matches!(report.find(["pool"], "max_conns"), Some(ItemOutcome::Rejected(_))));
+    assert!(!pool.update());
+    assert_eq!((pool.min_conns, pool.max_conns), (15, 20));
+}
+
+#[test]
+fn validate_concurrent_commit() {
+    let storage = config_it::create_storage();
+    let pool = storage.create::<Pool>(["pool"]).unwrap().updated();
+
+    // Each commit alone is valid against the initial state, but not together.
+    let workers: Vec<_> = [(8, 1), (4, 10)]
+        .into_iter()
+        .enumerate()
+        .map(|(field, (a, b))| {
+            let mut pool = pool.clone();
+            let mut observer = pool.clone();
+            std::thread::spawn(move || {
+                for iter in 0..20000 {
+                    let value = if iter % 2 == 0 { a } else { b };
+                    if field == 0 {
+                        pool.min_conns = value;
+                        let _ = pool.try_commit_elem(&pool.min_conns, true);
+                    } else {
+                        pool.max_conns = value;
+                        let _ = pool.try_commit_elem(&pool.max_conns, true);
+                    }
+
+                    observer.update();
+                    assert!(observer.min_conns <= observer.max_conns);
+                }
+            })
+        })
+        .collect();
+
+    workers.into_iter().for_each(|x| x.join().unwrap());
+}
+
+#[test]
+fn validate_strict_import() {
+    let storage = config_it::create_storage();
+    let mut first = storage.create::<Pool>(["first"]).unwrap().updated();
+    let mut second = storage.create::<Pool>(["second"]).unwrap().updated();
+    storage.import(archive(r#"{ "~second": { "min_conns": 15, "max_conns": 20 } }"#));
+    assert!(second.update());
+
+    // Resetting `max_conns` of the second group makes it invalid, which aborts the first group too.
+    let report = storage
+        .import(archive(r#"{ "~first": { "idle": 7 }, "~second": { "min_conns": 15 } }"#))
+        .merge_onto_cache(false)
+        .strict(true)
+        .apply();
+
+    assert!(report.aborted);
+    assert_eq!(report.find(["first"], "idle"), Some(&ItemOutcome::Applied));
+    assert!(matches!(report.find(["second"], "max_conns"), Some(ItemOutcome::Rejected(_))));
+    assert!(!first.update() && !second.update());
+
+    // So does the template validator rejecting the upserted values.
+    let report = storage
+        .import(archive(r#"{ "~first": { "idle": 7 }, "~second": { "max_conns": 2 } }"#))
+        .strict(true)
+        .apply();
+
+    assert!(report.aborted);
+    assert!(!first.update() && !second.update());
+    assert_eq!((first.idle, second.max_conns), (5, 20));
+
+    let report = storage
+        .import(archive(r#"{ "~first": { "idle": 7 }, "~second": { "max_conns": 30 } }"#))
+        .strict(true)
+        .apply();
+
+    assert!(!report.aborted);
+    assert!(first.update() && second.update());
+    assert_eq!((first.idle, second.max_conns), (7, 30));
+}
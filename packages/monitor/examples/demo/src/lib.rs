@@ -1,13 +1,83 @@
+use std::sync::Arc;
+
+use config_it::Group;
+use config_it_egui::{egui::Inspector, Subscriber};
+
 mod demo_cfg {
     #[derive(config_it::Template, Clone)]
-    pub struct DemoConfig1 {}
+    pub struct DemoConfig1 {
+        /// Name displayed on title
+        #[config(default = "demo")]
+        pub title: String,
+
+        #[config(default = 30, min = 1, max = 240)]
+        pub fps: u32,
+
+        #[config(default = 1.0, min = 0.1, max = 4.0)]
+        pub scale: f64,
+
+        #[config(default = true)]
+        pub vsync: bool,
+
+        #[config(default = "info", one_of = ["trace", "debug", "info", "warn", "error"])]
+        pub log_level: String,
+
+        #[config(default_expr = "[255, 128, 0, 255]", editor = ColorRgba255)]
+        pub accent: [u8; 4],
+
+        #[config(default = "", editor = MultilineText)]
+        pub notes: String,
+
+        #[config(default = "hunter2", secret)]
+        pub password: String,
+
+        #[config(default = 42, readonly)]
+        pub build_number: u32,
+
+        #[config(hidden)]
+        pub internal: Vec<u32>,
+    }
 }
 
-#[derive(Default)]
-pub struct App {}
+pub struct App {
+    _storage: config_it::Storage,
+    subscriber: Arc<Subscriber>,
+    inspector: Inspector,
+    groups: Vec<Group<demo_cfg::DemoConfig1>>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        let storage = config_it::create_storage();
+        let subscriber = Subscriber::new(&storage);
+        let groups = [["app", "window"], ["app", "renderer"]]
+            .into_iter()
+            .map(|path| storage.create(path).unwrap())
+            .collect();
+
+        Self { _storage: storage, subscriber, inspector: Inspector::default(), groups }
+    }
+}
 
 impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        todo!()
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        for group in &mut self.groups {
+            group.update();
+        }
+
+        egui::SidePanel::left("inspector").show(ctx, |ui| {
+            self.inspector.show(ui, &self.subscriber);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            for group in &self.groups {
+                ui.label(format!(
+                    "{}: fps={} scale={}",
+                    group.path().iter().collect::<Vec<_>>().join("."),
+                    group.fps,
+                    group.scale
+                ));
+            }
+        });
     }
 }
@@ -0,0 +1,584 @@
+//! Property inspector widget for [`egui`](::egui).
+//!
+//! The widget renders every group mirrored by a [`Subscriber`] as a tree, which is organized by
+//! group path. Editors are chosen based on each item's schema, editor hint and flags, and
+//! committed editions are written back to the storage via [`Subscriber::commit`].
+
+use std::collections::HashMap;
+
+use ::egui::{self, Color32, RichText, Ui};
+use config_it::{
+    meta::{MetaFlag, MetadataEditorHint},
+    schemars::schema::{InstanceType, SingleOrVec},
+    shared::{GroupId, ItemId},
+};
+
+use crate::{GroupMirror, ItemMirror, Subscriber};
+
+/// Inspector widget state. Keep single instance per view, as it retains editing buffers between
+/// frames.
+pub struct Inspector {
+    id: egui::Id,
+
+    /// Show items with [`MetaFlag::HIDDEN`] and [`MetaFlag::HIDDEN_NON_ADMIN`] flags.
+    pub show_hidden: bool,
+
+    /// Show undo/redo toolbar on top of the tree.
+    pub show_toolbar: bool,
+
+    /// Only groups whose path contains this text are displayed.
+    pub filter: String,
+
+    pending: HashMap<(GroupId, ItemId), Pending>,
+    errors: HashMap<(GroupId, ItemId), String>,
+}
+
+/// Uncommitted edition of single item.
+enum Pending {
+    Value(serde_json::Value),
+    Text(String),
+}
+
+/// Editor type, which is determined from item metadata.
+enum EditorKind {
+    Bool,
+    Integer { min: Option<f64>, max: Option<f64> },
+    Number { min: Option<f64>, max: Option<f64> },
+    Text { multiline: bool, code: bool },
+    Enum(Vec<serde_json::Value>),
+    Color { real: bool },
+    Json,
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self::new("config-it-inspector")
+    }
+}
+
+impl Inspector {
+    pub fn new(id_source: impl std::hash::Hash) -> Self {
+        Self {
+            id: egui::Id::new(id_source),
+            show_hidden: false,
+            show_toolbar: true,
+            filter: Default::default(),
+            pending: Default::default(),
+            errors: Default::default(),
+        }
+    }
+
+    /// Requests repaint of given context whenever the subscriber receives any event.
+    pub fn attach_repaint(ctx: &egui::Context, subscriber: &Subscriber) {
+        let ctx = ctx.clone();
+        subscriber.set_notify_hook(move || ctx.request_repaint());
+    }
+
+    /// Renders the inspector.
+    pub fn show(&mut self, ui: &mut Ui, subscriber: &Subscriber) {
+        if self.show_toolbar {
+            self.toolbar(ui, subscriber);
+            ui.separator();
+        }
+
+        // Render from cloned mirror; committing an edition while the mirror is locked would
+        // deadlock, as the subscriber receives its own update synchronously.
+        let groups = subscriber.groups();
+        let groups: Vec<_> = groups
+            .iter()
+            .filter(|g| self.filter.is_empty() || g.path.join(".").contains(&self.filter))
+            .collect();
+
+        egui::ScrollArea::vertical().id_source(self.id.with("scroll")).show(ui, |ui| {
+            self.tree(ui, subscriber, &groups, 0);
+        });
+    }
+
+    fn toolbar(&mut self, ui: &mut Ui, subscriber: &Subscriber) {
+        ui.horizontal(|ui| {
+            if ui.add_enabled(subscriber.can_undo(), egui::Button::new("Undo")).clicked() {
+                if let Err(error) = subscriber.undo() {
+                    tr::warn!(%error, "undo failed");
+                }
+            }
+
+            if ui.add_enabled(subscriber.can_redo(), egui::Button::new("Redo")).clicked() {
+                if let Err(error) = subscriber.redo() {
+                    tr::warn!(%error, "redo failed");
+                }
+            }
+
+            ui.separator();
+            ui.label("Filter");
+            ui.text_edit_singleline(&mut self.filter);
+            ui.checkbox(&mut self.show_hidden, "Hidden");
+        });
+    }
+
+    /// Renders groups which share the same path prefix of length `depth`.
+    fn tree(
+        &mut self,
+        ui: &mut Ui,
+        subscriber: &Subscriber,
+        groups: &[&GroupMirror],
+        depth: usize,
+    ) {
+        let mut index = 0;
+
+        while index < groups.len() {
+            let group = groups[index];
+
+            if group.path.len() == depth + 1 {
+                // Leaf group; may have its own child groups right after it.
+                let end = index
+                    + 1
+                    + groups[index + 1..]
+                        .iter()
+                        .take_while(|g| g.path.starts_with(&group.path))
+                        .count();
+
+                let header = egui::CollapsingHeader::new(&group.path[depth])
+                    .id_source(self.id.with(&group.path))
+                    .default_open(depth == 0);
+
+                header.show(ui, |ui| {
+                    self.group_items(ui, subscriber, group);
+                    self.tree(ui, subscriber, &groups[index + 1..end], depth + 1);
+                });
+
+                index = end;
+            } else {
+                // Intermediate path component without group.
+                let key = &group.path[depth];
+                let end = index
+                    + groups[index..].iter().take_while(|g| g.path.get(depth) == Some(key)).count();
+
+                egui::CollapsingHeader::new(key)
+                    .id_source(self.id.with(&group.path[..=depth]))
+                    .default_open(depth == 0)
+                    .show(ui, |ui| self.tree(ui, subscriber, &groups[index..end], depth + 1));
+
+                index = end;
+            }
+        }
+    }
+
+    fn group_items(&mut self, ui: &mut Ui, subscriber: &Subscriber, group: &GroupMirror) {
+        let (module, name) = group.template_name;
+        ui.label(RichText::new(format!("{module}::{name}")).weak().small());
+
+        egui::Grid::new(self.id.with(group.group_id)).num_columns(2).striped(true).show(ui, |ui| {
+            for item in &group.items {
+                let flags = item.meta.flags;
+                if !self.show_hidden
+                    && (flags.contains(MetaFlag::HIDDEN)
+                        || flags.contains(MetaFlag::HIDDEN_NON_ADMIN))
+                {
+                    continue;
+                }
+
+                let mut label = RichText::new(item.meta.name);
+                if flags.contains(MetaFlag::READONLY) {
+                    label = label.weak();
+                }
+                if item.meta.deprecated.is_some() {
+                    label = label.strikethrough();
+                }
+
+                let label = ui.label(label);
+                let hover = [item.meta.description, item.meta.deprecated.unwrap_or_default()];
+                let hover = match hover {
+                    [description, ""] => description.to_owned(),
+                    ["", note] => format!("Deprecated: {note}"),
+                    [description, note] => format!("{description}\n\nDeprecated: {note}"),
+                };
+                if !hover.is_empty() {
+                    label.on_hover_text(hover);
+                }
+
+                ui.vertical(|ui| {
+                    self.item_editor(ui, subscriber, group.group_id, item);
+
+                    if let Some(error) = self.errors.get(&(group.group_id, item.item_id)) {
+                        ui.label(RichText::new(error).color(Color32::LIGHT_RED).small());
+                    }
+                });
+
+                ui.end_row();
+            }
+        });
+    }
+
+    fn item_editor(
+        &mut self,
+        ui: &mut Ui,
+        subscriber: &Subscriber,
+        group_id: GroupId,
+        item: &ItemMirror,
+    ) {
+        let key = (group_id, item.item_id);
+        let flags = item.meta.flags;
+        let secret = flags.contains(MetaFlag::SECRET);
+        let id = self.id.with(key);
+
+        let commit = ui
+            .add_enabled_ui(!flags.contains(MetaFlag::READONLY), |ui| {
+                let pending = self.pending.get_mut(&key);
+                edit_value(ui, id, EditorKind::from_item(item), secret, &item.value, pending)
+            })
+            .inner;
+
+        match commit {
+            EditResult::None => {}
+            EditResult::Editing(pending) => {
+                self.pending.insert(key, pending);
+            }
+            EditResult::Commit(value) => {
+                self.pending.remove(&key);
+                self.errors.remove(&key);
+
+                if let Err(error) = subscriber.commit(group_id, item.item_id, &value) {
+                    self.errors.insert(key, error.to_string());
+                }
+            }
+            EditResult::Error(error) => {
+                self.errors.insert(key, error);
+            }
+        }
+    }
+}
+
+enum EditResult {
+    None,
+    Editing(Pending),
+    Commit(serde_json::Value),
+    Error(String),
+}
+
+impl EditorKind {
+    fn from_item(item: &ItemMirror) -> Self {
+        match &item.meta.editor_hint {
+            Some(MetadataEditorHint::ColorRgba255) => return Self::Color { real: false },
+            Some(MetadataEditorHint::ColorRgbaReal) => return Self::Color { real: true },
+            Some(MetadataEditorHint::MultilineText) => {
+                return Self::Text { multiline: true, code: false }
+            }
+            Some(MetadataEditorHint::Code(_)) => return Self::Text { multiline: true, code: true },
+            _ => {}
+        }
+
+        let Some(schema) = &item.meta.schema else { return Self::Json };
+        let schema = &schema.schema;
+
+        if let Some(values) = &schema.enum_values {
+            return Self::Enum(values.clone());
+        }
+
+        let range = |x: &Option<Box<config_it::schemars::schema::NumberValidation>>| {
+            x.as_ref().map(|x| (x.minimum, x.maximum)).unwrap_or_default()
+        };
+
+        match &schema.instance_type {
+            Some(SingleOrVec::Single(ty)) => match **ty {
+                InstanceType::Boolean => Self::Bool,
+                InstanceType::Integer => {
+                    let (min, max) = range(&schema.number);
+                    Self::Integer { min, max }
+                }
+                InstanceType::Number => {
+                    let (min, max) = range(&schema.number);
+                    Self::Number { min, max }
+                }
+                InstanceType::String => Self::Text { multiline: false, code: false },
+                _ => Self::Json,
+            },
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Renders single editor. Editions are committed once the widget is released, i.e. it's neither
+/// dragged nor focused anymore.
+fn edit_value(
+    ui: &mut Ui,
+    id: egui::Id,
+    kind: EditorKind,
+    secret: bool,
+    current: &serde_json::Value,
+    pending: Option<&mut Pending>,
+) -> EditResult {
+    use serde_json::Value;
+
+    let value = match &pending {
+        Some(Pending::Value(x)) => x,
+        _ => current,
+    };
+
+    let released = |r: &egui::Response| !r.dragged() && !r.has_focus();
+    let finish = |r: egui::Response, value: Value| {
+        if r.changed() || pending.is_some() {
+            if released(&r) {
+                EditResult::Commit(value)
+            } else {
+                EditResult::Editing(Pending::Value(value))
+            }
+        } else {
+            EditResult::None
+        }
+    };
+
+    match kind {
+        EditorKind::Bool if value.is_boolean() => {
+            let mut v = value.as_bool().unwrap();
+            let r = ui.checkbox(&mut v, "");
+            finish(r, v.into())
+        }
+
+        EditorKind::Integer { min, max } if value.is_i64() || value.is_u64() => {
+            // Edited as text, as dragging through `f64` loses precision beyond 2^53.
+            let mut text = match pending {
+                Some(Pending::Text(ref x)) => x.clone(),
+                _ => value.to_string(),
+            };
+
+            let (step, r) = ui
+                .horizontal(|ui| {
+                    let dec = ui.small_button("-").clicked();
+                    let r = text_editor(ui, &mut text, false, false, secret);
+                    let inc = ui.small_button("+").clicked();
+                    (inc as i128 - dec as i128, r)
+                })
+                .inner;
+
+            match step_integer(value, step, min, max) {
+                Some(value) if step != 0 => EditResult::Commit(value),
+                _ => finish_text(r, text, pending.is_some(), |x| parse_integer(&x, min, max)),
+            }
+        }
+
+        EditorKind::Number { min, max } if value.is_number() => {
+            let mut v = value.as_f64().unwrap();
+            let range = min.unwrap_or(f64::MIN)..=max.unwrap_or(f64::MAX);
+            let r = ui.add(egui::DragValue::new(&mut v).clamp_range(range).speed(0.01));
+            finish(r, v.into())
+        }
+
+        EditorKind::Enum(values) => {
+            let mut selected = value.clone();
+            egui::ComboBox::from_id_source(id).selected_text(display_json(&selected)).show_ui(
+                ui,
+                |ui| {
+                    for v in &values {
+                        ui.selectable_value(&mut selected, v.clone(), display_json(v));
+                    }
+                },
+            );
+
+            if selected != *value {
+                EditResult::Commit(selected)
+            } else {
+                EditResult::None
+            }
+        }
+
+        EditorKind::Color { real } if color_len(value).is_some() => {
+            let arr = value.as_array().unwrap();
+            let mut rgba = color_to_rgba(arr, real);
+
+            let r = if arr.len() == 3 {
+                let mut rgb = [rgba[0], rgba[1], rgba[2]];
+                let r = ui.color_edit_button_rgb(&mut rgb);
+                rgba[..3].copy_from_slice(&rgb);
+                r
+            } else {
+                ui.color_edit_button_rgba_unmultiplied(&mut rgba)
+            };
+
+            // Color picker popup neither holds focus nor reports its drags on the button; keep the
+            // edition pending until the pointer is released.
+            let out = rgba_to_color(&rgba[..arr.len()], real);
+            if !(r.changed() || pending.is_some()) {
+                EditResult::None
+            } else if ui.input(|i| i.pointer.any_down()) {
+                EditResult::Editing(Pending::Value(out))
+            } else {
+                EditResult::Commit(out)
+            }
+        }
+
+        EditorKind::Text { multiline, code } if value.is_string() => {
+            let mut text = match pending {
+                Some(Pending::Text(ref x)) => x.clone(),
+                _ => value.as_str().unwrap().to_owned(),
+            };
+
+            let r = text_editor(ui, &mut text, multiline, code, secret);
+            finish_text(r, text, pending.is_some(), |x| Ok(Value::String(x)))
+        }
+
+        _ => {
+            // Fallback; edit raw JSON representation.
+            let mut text = match pending {
+                Some(Pending::Text(ref x)) => x.clone(),
+                _ => serde_json::to_string_pretty(value).unwrap_or_default(),
+            };
+
+            let multiline = value.is_object() || value.is_array();
+            let r = text_editor(ui, &mut text, multiline, true, secret);
+            finish_text(r, text, pending.is_some(), |x| {
+                serde_json::from_str(&x).map_err(|e| format!("Invalid JSON: {e}"))
+            })
+        }
+    }
+}
+
+fn text_editor(
+    ui: &mut Ui,
+    text: &mut String,
+    multiline: bool,
+    code: bool,
+    secret: bool,
+) -> egui::Response {
+    let mut edit = if multiline && !secret {
+        egui::TextEdit::multiline(text).desired_rows(1)
+    } else {
+        egui::TextEdit::singleline(text)
+    };
+
+    if code {
+        edit = edit.code_editor();
+    }
+
+    ui.add(edit.password(secret))
+}
+
+fn finish_text(
+    r: egui::Response,
+    text: String,
+    has_pending: bool,
+    parse: impl FnOnce(String) -> Result<serde_json::Value, String>,
+) -> EditResult {
+    if !(r.changed() || has_pending) {
+        return EditResult::None;
+    }
+
+    if r.has_focus() {
+        return EditResult::Editing(Pending::Text(text));
+    }
+
+    match parse(text) {
+        Ok(value) => EditResult::Commit(value),
+        Err(error) => EditResult::Error(error),
+    }
+}
+
+fn color_len(value: &serde_json::Value) -> Option<usize> {
+    let arr = value.as_array()?;
+    (matches!(arr.len(), 3 | 4) && arr.iter().all(|x| x.is_number())).then_some(arr.len())
+}
+
+/// Normalizes color components into `0..=1` range. Missing alpha channel is opaque.
+fn color_to_rgba(arr: &[serde_json::Value], real: bool) -> [f32; 4] {
+    let mut rgba = [1.0f32; 4];
+    for (dst, src) in rgba.iter_mut().zip(arr) {
+        let src = src.as_f64().unwrap_or_default() as f32;
+        *dst = if real { src } else { src / 255. };
+    }
+    rgba
+}
+
+fn rgba_to_color(rgba: &[f32], real: bool) -> serde_json::Value {
+    rgba.iter()
+        .map(|&x| {
+            if real {
+                serde_json::Value::from(x as f64)
+            } else {
+                serde_json::Value::from((x * 255.).round() as u8)
+            }
+        })
+        .collect()
+}
+
+/// Inclusive integer range allowed by the schema, within the domain of `i64` and `u64`.
+fn integer_bounds(min: Option<f64>, max: Option<f64>) -> (i128, i128) {
+    let lo = min.map_or(i128::MIN, |x| x.ceil() as i128).max(i64::MIN.into());
+    let hi = max.map_or(i128::MAX, |x| x.floor() as i128).min(u64::MAX.into());
+    (lo, hi)
+}
+
+fn integer_to_value(value: i128) -> serde_json::Value {
+    match u64::try_from(value) {
+        Ok(x) => x.into(),
+        Err(_) => (value as i64).into(),
+    }
+}
+
+fn parse_integer(
+    text: &str,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> Result<serde_json::Value, String> {
+    let value: i128 = text.trim().parse().map_err(|e| format!("Invalid integer: {e}"))?;
+    let (lo, hi) = integer_bounds(min, max);
+
+    if value < lo || value > hi {
+        return Err(format!("Out of range: {lo}..={hi}"));
+    }
+
+    Ok(integer_to_value(value))
+}
+
+/// Adds `step` to the integer value, saturating at the bounds.
+fn step_integer(
+    value: &serde_json::Value,
+    step: i128,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> Option<serde_json::Value> {
+    let value = value.as_i64().map(i128::from).or_else(|| value.as_u64().map(i128::from))?;
+    let (lo, hi) = integer_bounds(min, max);
+    Some(integer_to_value((value + step).min(hi).max(lo)))
+}
+
+fn display_json(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(x) => x.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[test]
+fn integer_mapping_is_exact() {
+    use serde_json::json;
+
+    let big = (1u64 << 53) + 1;
+    assert_eq!(parse_integer(&big.to_string(), None, None), Ok(json!(big)));
+    assert_eq!(parse_integer(" 18446744073709551615 ", None, None), Ok(json!(u64::MAX)));
+    assert_eq!(parse_integer("-9223372036854775808", None, None), Ok(json!(i64::MIN)));
+    assert_eq!(parse_integer("-3", Some(-5.), Some(5.)), Ok(json!(-3)));
+
+    assert!(parse_integer("18446744073709551616", None, None).is_err());
+    assert!(parse_integer("6", Some(-5.), Some(5.)).is_err());
+    assert!(parse_integer("1.5", None, None).is_err());
+
+    assert_eq!(step_integer(&json!(big), 1, None, None), Some(json!(big + 1)));
+    assert_eq!(step_integer(&json!(u64::MAX), 1, None, None), Some(json!(u64::MAX)));
+    assert_eq!(step_integer(&json!(0), -1, None, None), Some(json!(-1)));
+    assert_eq!(step_integer(&json!(5), 1, Some(0.), Some(5.)), Some(json!(5)));
+    assert_eq!(step_integer(&json!("5"), 1, None, None), None);
+}
+
+#[test]
+fn color_mapping_round_trips() {
+    use serde_json::json;
+
+    let rgb = json!([0, 128, 255]);
+    let rgba = color_to_rgba(rgb.as_array().unwrap(), false);
+    assert_eq!(rgba[3], 1.);
+    assert_eq!(rgba_to_color(&rgba[..3], false), rgb);
+
+    let real = json!([0.25, 0.5, 0.75, 0.5]);
+    let rgba = color_to_rgba(real.as_array().unwrap(), true);
+    assert_eq!(rgba, [0.25, 0.5, 0.75, 0.5]);
+    assert_eq!(rgba_to_color(&rgba, true), real);
+}
@@ -0,0 +1,104 @@
+//! Bounded edition history of a subscribed storage.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use config_it::{
+    config::storage::UpdateOrigin,
+    shared::{GroupId, ItemId},
+};
+
+/// Capacity settings of the edition history.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryConfig {
+    /// Maximum number of undoable records kept for the whole storage.
+    pub capacity: usize,
+
+    /// Maximum number of records kept for each item.
+    pub item_capacity: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { capacity: 256, item_capacity: 32 }
+    }
+}
+
+/// Single recorded edition of an item.
+#[derive(Debug, Clone)]
+pub struct EditRecord {
+    pub group_id: GroupId,
+    pub item_id: ItemId,
+
+    /// Path of the group which owns the item.
+    pub path: Arc<[String]>,
+
+    /// Archive name of the item.
+    pub name: &'static str,
+
+    /// Value before the edition.
+    pub old: serde_json::Value,
+
+    /// Value after the edition.
+    pub new: serde_json::Value,
+
+    pub timestamp: SystemTime,
+    pub origin: UpdateOrigin,
+}
+
+#[derive(Default)]
+pub(crate) struct History {
+    pub config: HistoryConfig,
+    pub undo: VecDeque<Arc<EditRecord>>,
+    pub redo: Vec<Arc<EditRecord>>,
+    pub items: HashMap<ItemId, VecDeque<Arc<EditRecord>>>,
+}
+
+impl History {
+    pub fn new(config: HistoryConfig) -> Self {
+        Self { config, ..Default::default() }
+    }
+
+    /// Records new edition. Replayed records (undo/redo) are only kept in item-wise history, to not
+    /// break the undo/redo stacks.
+    pub fn record(&mut self, record: EditRecord, replayed: bool) {
+        let record = Arc::new(record);
+        let item_capacity = self.config.item_capacity;
+
+        if item_capacity > 0 {
+            let items = self.items.entry(record.item_id).or_default();
+            if items.len() >= item_capacity {
+                items.pop_front();
+            }
+
+            items.push_back(record.clone());
+        }
+
+        if replayed || self.config.capacity == 0 {
+            return;
+        }
+
+        if self.undo.len() >= self.config.capacity {
+            self.undo.pop_front();
+        }
+
+        self.undo.push_back(record);
+        self.redo.clear();
+    }
+
+    /// Discards every record of given group, as its item IDs will never appear again.
+    pub fn forget_group(&mut self, group_id: GroupId) {
+        self.undo.retain(|x| x.group_id != group_id);
+        self.redo.retain(|x| x.group_id != group_id);
+        self.items.retain(|_, v| v.front().is_some_and(|x| x.group_id != group_id));
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.items.clear();
+    }
+}
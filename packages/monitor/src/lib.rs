@@ -4,10 +4,14 @@
 //! - JsonSchema retrieval from every items
 //! - Edition history support.
 
+mod history;
 mod subscriber;
 
+pub use history::{EditRecord, HistoryConfig};
+pub use subscriber::{EditError, GroupMirror, ItemMirror, Subscriber, SubscriberEvent};
+
 #[cfg(feature = "egui")]
-mod egui {}
+pub mod egui;
 
 #[cfg(any())]
 mod dioxus {}
@@ -1,13 +1,460 @@
-mod schema {}
-
 /* ============================================================================================== */
 /*                                           SUBSCRIBER                                           */
 /* ============================================================================================== */
 
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+
+use config_it::{
+    config::{
+        entity::{EntityData, EntityUpdateError, PropertyInfo},
+        group::GroupContext,
+        storage::{current_update_origin, with_update_origin, MonitorClosed, UpdateOrigin},
+    },
+    meta::MetaFlag,
+    shared::{GroupId, ItemId, StorageId},
+    Monitor, Storage, Validation,
+};
+use parking_lot::{Mutex, RwLock};
+
+use crate::history::{EditRecord, History, HistoryConfig};
+
+/// Maximum number of pending change events. If the consumer does not drain the feed in time, the
+/// queue collapses into single [`SubscriberEvent::Overflow`] event.
+const MAX_PENDING_EVENTS: usize = 4096;
+
 /// Subscriber object links to single [`config_it::Storage`] object, receives all updates from it
 /// then interprets every single editable objects into more handy·controllable data type.
-pub struct Subscriber {}
+///
+/// The subscriber keeps its own mirror of every group registered to the storage, therefore UI
+/// code can freely poll it without touching any of the storage locks.
+pub struct Subscriber {
+    storage_id: StorageId,
+    groups: RwLock<HashMap<GroupId, GroupMirror>>,
+    events: Mutex<EventQueue>,
+
+    /// Incremented on every mirror modification.
+    revision: AtomicU64,
+    closed: AtomicBool,
+
+    /// Called whenever new event is queued. e.g. request repaint of UI.
+    #[allow(clippy::type_complexity)]
+    notify_hook: RwLock<Option<Box<dyn Fn() + Send + Sync>>>,
+
+    history: Mutex<History>,
+}
+
+thread_local! {
+    /// Address of the subscriber which is currently replaying its history on this thread.
+    static REPLAYING: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EditError {
+    #[error("Group {0} is not found")]
+    GroupNotFound(GroupId),
+
+    #[error("Item {0} is not found")]
+    ItemNotFound(ItemId),
+
+    #[error("Item is read-only")]
+    ReadOnly,
+
+    #[error(transparent)]
+    UpdateFailed(#[from] EntityUpdateError),
+}
+
+/// Mirrored state of single group.
+#[derive(Debug, Clone)]
+pub struct GroupMirror {
+    pub group_id: GroupId,
+
+    /// Path of the group, as provided on group creation.
+    pub path: Arc<[String]>,
+
+    /// Module path and type name of the template.
+    pub template_name: (&'static str, &'static str),
+
+    /// List of items, in order of template declaration.
+    pub items: Vec<ItemMirror>,
+
+    context: Arc<GroupContext>,
+}
+
+/// Mirrored state of single item.
+#[derive(Debug, Clone)]
+pub struct ItemMirror {
+    pub item_id: ItemId,
+    pub meta: &'static PropertyInfo,
+
+    /// Latest JSON representation of the value.
+    pub value: serde_json::Value,
+
+    /// Subscriber revision when this item was lastly updated.
+    pub revision: u64,
+}
+
+/// Change feed event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriberEvent {
+    GroupAdded(GroupId),
+    GroupRemoved(GroupId),
+    ValueUpdated(GroupId, ItemId),
+
+    /// Change feed was not drained in time, and some events were discarded. Consumer should treat
+    /// every group as changed.
+    Overflow,
+}
+
+#[derive(Default)]
+struct EventQueue {
+    queue: VecDeque<SubscriberEvent>,
+    overflowed: bool,
+}
+
+impl GroupMirror {
+    /// Finds an item by its ID.
+    pub fn find_item(&self, item_id: ItemId) -> Option<&ItemMirror> {
+        self.items.iter().find(|x| x.item_id == item_id)
+    }
+
+    /// Finds an item by its archive name.
+    pub fn find_item_by_name(&self, name: &str) -> Option<&ItemMirror> {
+        self.items.iter().find(|x| x.meta.name == name)
+    }
+}
 
 impl Subscriber {
-    // TODO:
+    /// Creates new subscriber and registers it to given storage as monitor. Every group that is
+    /// already registered to the storage is mirrored immediately.
+    pub fn new(storage: &Storage) -> Arc<Self> {
+        Self::with_history(storage, Default::default())
+    }
+
+    /// Creates new subscriber with given edition history capacity.
+    pub fn with_history(storage: &Storage, config: HistoryConfig) -> Arc<Self> {
+        let this = Arc::new(Self {
+            storage_id: storage.storage_id(),
+            groups: Default::default(),
+            events: Default::default(),
+            revision: AtomicU64::new(1),
+            closed: AtomicBool::new(false),
+            notify_hook: Default::default(),
+            history: Mutex::new(History::new(config)),
+        });
+
+        storage.add_monitor(this.clone());
+        this
+    }
+
+    /// ID of the storage which this subscriber is linked to.
+    pub fn storage_id(&self) -> StorageId {
+        self.storage_id
+    }
+
+    /// Detaches this subscriber from the storage. The storage will dispose it on its next event.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Current revision of the mirror. Any modification to the mirror increments this value.
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::Acquire)
+    }
+
+    /// Registers a hook which is invoked every time a new event is queued. The hook is called from
+    /// storage thread, therefore it must not block.
+    pub fn set_notify_hook(&self, hook: impl Fn() + Send + Sync + 'static) {
+        *self.notify_hook.write() = Some(Box::new(hook));
+    }
+
+    /// Drains all pending change events.
+    pub fn poll_events(&self) -> Vec<SubscriberEvent> {
+        let mut events = self.events.lock();
+        let mut drained: Vec<_> = events.queue.drain(..).collect();
+
+        if std::mem::take(&mut events.overflowed) {
+            drained.push(SubscriberEvent::Overflow);
+        }
+
+        drained
+    }
+
+    /// Lists every mirrored group ID.
+    pub fn group_ids(&self) -> Vec<GroupId> {
+        self.groups.read().keys().copied().collect()
+    }
+
+    /// Visits single group mirror.
+    pub fn with_group<R>(&self, group_id: GroupId, f: impl FnOnce(&GroupMirror) -> R) -> Option<R> {
+        self.groups.read().get(&group_id).map(f)
+    }
+
+    /// Visits every group mirror, in ascending order of path.
+    pub fn visit_groups(&self, f: impl FnMut(&GroupMirror)) {
+        let groups = self.groups.read();
+        let mut sorted: Vec<_> = groups.values().collect();
+        sorted.sort_by(|a, b| a.path.cmp(&b.path));
+        sorted.into_iter().for_each(f);
+    }
+
+    /// Clones all group mirrors, in ascending order of path.
+    pub fn groups(&self) -> Vec<GroupMirror> {
+        let mut groups = Vec::new();
+        self.visit_groups(|x| groups.push(x.clone()));
+        groups
+    }
+
+    /// Gets latest value of given item.
+    pub fn value(&self, group_id: GroupId, item_id: ItemId) -> Option<serde_json::Value> {
+        self.with_group(group_id, |g| g.find_item(item_id).map(|x| x.value.clone())).flatten()
+    }
+
+    /// Writes new value to the storage, as a monitor. The edition is recorded to the history, and
+    /// every group instance which shares the item gets notified.
+    pub fn commit(
+        &self,
+        group_id: GroupId,
+        item_id: ItemId,
+        value: &serde_json::Value,
+    ) -> Result<Validation, EditError> {
+        let (context, meta) = self.find_entity(group_id, item_id)?;
+        if meta.flags.contains(MetaFlag::READONLY) {
+            return Err(EditError::ReadOnly);
+        }
+
+        Self::write_entity(&context, item_id, value)
+    }
+
+    /// Reverts the latest edition of the storage, by writing its old value back. Returns `None` if
+    /// there's nothing to undo.
+    ///
+    /// If the reverted item no longer accepts the old value, the error is returned and the record
+    /// stays on top of the undo stack.
+    pub fn undo(&self) -> Result<Option<Arc<EditRecord>>, EditError> {
+        let Some(record) = self.history.lock().undo.pop_back() else { return Ok(None) };
+        if let Err(error) = self.replay(&record, &record.old) {
+            self.history.lock().undo.push_back(record);
+            return Err(error);
+        }
+
+        self.history.lock().redo.push(record.clone());
+        Ok(Some(record))
+    }
+
+    /// Re-applies the latest undone edition. Returns `None` if there's nothing to redo.
+    ///
+    /// If the item no longer accepts the new value, the error is returned and the record stays on
+    /// top of the redo stack.
+    pub fn redo(&self) -> Result<Option<Arc<EditRecord>>, EditError> {
+        let Some(record) = self.history.lock().redo.pop() else { return Ok(None) };
+        if let Err(error) = self.replay(&record, &record.new) {
+            self.history.lock().redo.push(record);
+            return Err(error);
+        }
+
+        self.history.lock().undo.push_back(record.clone());
+        Ok(Some(record))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.lock().undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.lock().redo.is_empty()
+    }
+
+    /// Lists undoable editions of the storage, from oldest to latest.
+    pub fn history(&self) -> Vec<Arc<EditRecord>> {
+        self.history.lock().undo.iter().cloned().collect()
+    }
+
+    /// Lists recorded editions of given item, from oldest to latest.
+    pub fn item_history(&self, item_id: ItemId) -> Vec<Arc<EditRecord>> {
+        let history = self.history.lock();
+        history.items.get(&item_id).map(|x| x.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    pub fn clear_history(&self) {
+        self.history.lock().clear();
+    }
+
+    fn replay(&self, record: &EditRecord, value: &serde_json::Value) -> Result<(), EditError> {
+        struct Restore(usize);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                REPLAYING.with(|x| x.set(self.0));
+            }
+        }
+
+        let (context, _) = self.find_entity(record.group_id, record.item_id)?;
+        let _restore = Restore(REPLAYING.with(|x| x.replace(self as *const _ as usize)));
+
+        Self::write_entity(&context, record.item_id, value).map(|_| ())
+    }
+
+    fn find_entity(
+        &self,
+        group_id: GroupId,
+        item_id: ItemId,
+    ) -> Result<(Arc<GroupContext>, &'static PropertyInfo), EditError> {
+        let context = self
+            .groups
+            .read()
+            .get(&group_id)
+            .map(|x| x.context.clone())
+            .ok_or(EditError::GroupNotFound(group_id))?;
+
+        let meta = context.find_item(item_id).ok_or(EditError::ItemNotFound(item_id))?.meta;
+        Ok((context, meta))
+    }
+
+    fn write_entity(
+        context: &GroupContext,
+        item_id: ItemId,
+        value: &serde_json::Value,
+    ) -> Result<Validation, EditError> {
+        let entity = context.find_item(item_id).ok_or(EditError::ItemNotFound(item_id))?;
+
+        // Notification is delivered synchronously; this subscriber will receive the update event
+        // inside of this call.
+        with_update_origin(UpdateOrigin::Monitor, || Ok(context.commit_value_from(entity, value)?))
+    }
+
+    fn push_event(&self, event: SubscriberEvent) {
+        {
+            let mut events = self.events.lock();
+            if events.queue.len() >= MAX_PENDING_EVENTS {
+                events.queue.clear();
+                events.overflowed = true;
+            }
+
+            events.queue.push_back(event);
+        }
+
+        if let Some(hook) = &*self.notify_hook.read() {
+            hook();
+        }
+    }
+
+    fn next_revision(&self) -> u64 {
+        self.revision.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    fn check_closed(&self) -> Result<(), MonitorClosed> {
+        if self.is_closed() {
+            Err(MonitorClosed)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn serialize_entity(entity: &EntityData) -> serde_json::Value {
+    entity.serialize_into(serde_json::value::Serializer).unwrap_or_else(|error| {
+        tr::warn!(%error, name = entity.meta.name, "failed to serialize entity value");
+        serde_json::Value::Null
+    })
+}
+
+impl Monitor for Subscriber {
+    fn should_dispose(&self) -> bool {
+        self.is_closed()
+    }
+
+    fn group_added(
+        &self,
+        group_id: GroupId,
+        group: &Arc<GroupContext>,
+    ) -> Result<(), MonitorClosed> {
+        self.check_closed()?;
+
+        let revision = self.next_revision();
+        let mirror = GroupMirror {
+            group_id,
+            path: group.path.iter().map(ToOwned::to_owned).collect(),
+            template_name: group.template_name,
+            items: group
+                .entities()
+                .iter()
+                .map(|e| ItemMirror {
+                    item_id: e.id,
+                    meta: e.meta,
+                    value: serialize_entity(e),
+                    revision,
+                })
+                .collect(),
+            context: group.clone(),
+        };
+
+        self.groups.write().insert(group_id, mirror);
+        self.push_event(SubscriberEvent::GroupAdded(group_id));
+        Ok(())
+    }
+
+    fn group_removed(&self, group_id: GroupId) -> Result<(), MonitorClosed> {
+        self.check_closed()?;
+
+        if self.groups.write().remove(&group_id).is_some() {
+            self.history.lock().forget_group(group_id);
+            self.next_revision();
+            self.push_event(SubscriberEvent::GroupRemoved(group_id));
+        }
+
+        Ok(())
+    }
+
+    fn entity_value_updated(
+        &self,
+        group_id: GroupId,
+        item_id: ItemId,
+    ) -> Result<(), MonitorClosed> {
+        self.check_closed()?;
+
+        let record = {
+            let mut groups = self.groups.write();
+            let Some(group) = groups.get_mut(&group_id) else { return Ok(()) };
+            let Some(entity) = group.context.find_item(item_id) else { return Ok(()) };
+            let value = serialize_entity(entity);
+
+            let Some(item) = group.items.iter_mut().find(|x| x.item_id == item_id) else {
+                return Ok(());
+            };
+
+            let old = std::mem::replace(&mut item.value, value);
+            item.revision = self.next_revision();
+
+            (old != item.value).then(|| EditRecord {
+                group_id,
+                item_id,
+                path: group.path.clone(),
+                name: item.meta.name,
+                old,
+                new: item.value.clone(),
+                timestamp: SystemTime::now(),
+                origin: current_update_origin(),
+            })
+        };
+
+        if let Some(record) = record {
+            let replayed = REPLAYING.with(|x| x.get()) == self as *const _ as usize;
+            self.history.lock().record(record, replayed);
+        }
+
+        self.push_event(SubscriberEvent::ValueUpdated(group_id, item_id));
+        Ok(())
+    }
 }
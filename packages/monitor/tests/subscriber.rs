@@ -0,0 +1,198 @@
+use config_it_egui::{Subscriber, SubscriberEvent};
+
+#[derive(config_it::Template, Clone)]
+struct Net {
+    #[config(default = "localhost")]
+    host: String,
+
+    #[config(default = 8080)]
+    port: u16,
+}
+
+#[test]
+fn mirror_tracks_storage() {
+    let storage = config_it::create_storage();
+    let early = storage.create::<Net>(["net", "early"]).unwrap();
+
+    let subscriber = Subscriber::new(&storage);
+    assert_eq!(subscriber.poll_events(), [SubscriberEvent::GroupAdded(early.group_id())]);
+
+    let mut late = storage.create::<Net>(["net", "late"]).unwrap();
+    assert_eq!(subscriber.poll_events(), [SubscriberEvent::GroupAdded(late.group_id())]);
+
+    let paths: Vec<_> = subscriber.groups().iter().map(|x| x.path.join(".")).collect();
+    assert_eq!(paths, ["net.early", "net.late"]);
+
+    late.port = 9090;
+    late.commit_elem(&late.port, true);
+
+    let events = subscriber.poll_events();
+    let [SubscriberEvent::ValueUpdated(group_id, item_id)] = events[..] else {
+        panic!("unexpected events: {events:?}")
+    };
+
+    assert_eq!(group_id, late.group_id());
+    assert_eq!(subscriber.value(group_id, item_id), Some(serde_json::json!(9090)));
+    subscriber
+        .with_group(group_id, |g| {
+            assert_eq!(g.find_item_by_name("host").unwrap().value, "localhost");
+            assert_eq!(g.find_item(item_id).unwrap().meta.name, "port");
+        })
+        .unwrap();
+
+    let archive = serde_json::json!({ "~net": { "~early": { "host": "example.com" } } });
+    storage.import(serde_json::from_value(archive).unwrap());
+
+    let events = subscriber.poll_events();
+    assert!(matches!(events[..], [SubscriberEvent::ValueUpdated(g, _)] if g == early.group_id()));
+
+    drop(late);
+    assert_eq!(subscriber.poll_events(), [SubscriberEvent::GroupRemoved(group_id)]);
+    assert_eq!(subscriber.group_ids(), [early.group_id()]);
+
+    subscriber.close();
+    drop(early);
+    assert!(subscriber.poll_events().is_empty());
+}
+
+#[test]
+fn history_undo_redo() {
+    use config_it::config::storage::UpdateOrigin;
+
+    let storage = config_it::create_storage();
+    let subscriber = Subscriber::new(&storage);
+    let mut net = storage.create::<Net>(["net"]).unwrap().updated();
+    let port_id = subscriber.with_group(net.group_id(), |g| g.items[1].item_id).unwrap();
+
+    net.port = 1000;
+    net.commit_elem(&net.port, true);
+
+    let archive = serde_json::json!({ "~net": { "port": 2000 } });
+    storage.import(serde_json::from_value(archive).unwrap());
+
+    subscriber.commit(net.group_id(), port_id, &serde_json::json!(3000)).unwrap();
+    assert!(subscriber.commit(net.group_id(), port_id, &serde_json::json!("text")).is_err());
+
+    let origins: Vec<_> = subscriber.history().iter().map(|x| x.origin).collect();
+    assert_eq!(origins, [UpdateOrigin::Commit, UpdateOrigin::Import, UpdateOrigin::Monitor]);
+
+    let record = subscriber.undo().unwrap().unwrap();
+    assert_eq!((record.old.clone(), record.new.clone()), (2000.into(), 3000.into()));
+    assert!(net.update());
+    assert_eq!(net.port, 2000);
+
+    subscriber.undo().unwrap().unwrap();
+    assert!(net.update());
+    assert_eq!(net.port, 1000);
+
+    subscriber.redo().unwrap().unwrap();
+    assert!(net.update());
+    assert_eq!(net.port, 2000);
+    assert!(subscriber.can_redo());
+
+    // New edition discards redo stack.
+    net.port = 4000;
+    net.commit_elem(&net.port, true);
+    assert!(!subscriber.can_redo());
+    assert_eq!(subscriber.history().len(), 3);
+
+    // Replayed editions are kept in item history.
+    let values: Vec<_> = subscriber.item_history(port_id).iter().map(|x| x.new.clone()).collect();
+    assert_eq!(values, [1000, 2000, 3000, 2000, 1000, 2000, 4000].map(serde_json::Value::from));
+
+    drop(net);
+    assert!(!subscriber.can_undo());
+    assert!(subscriber.undo().unwrap().is_none());
+}
+
+#[derive(config_it::Template, Clone)]
+#[config(validate = "Range::validate")]
+struct Range {
+    #[config(default = 0)]
+    min: i32,
+
+    #[config(default = 10)]
+    max: i32,
+}
+
+impl Range {
+    fn validate(&mut self) -> Result<config_it::Validation, std::borrow::Cow<'static, str>> {
+        if self.min > self.max {
+            return Err("min exceeds max".into());
+        }
+
+        Ok(config_it::Validation::Valid)
+    }
+}
+
+#[test]
+fn commit_runs_template_validator() {
+    use config_it::config::entity::EntityUpdateError;
+    use config_it_egui::EditError;
+
+    let storage = config_it::create_storage();
+    let subscriber = Subscriber::new(&storage);
+    let mut range = storage.create::<Range>(["range"]).unwrap().updated();
+    let min_id = subscriber.with_group(range.group_id(), |g| g.items[0].item_id).unwrap();
+
+    let result = subscriber.commit(range.group_id(), min_id, &serde_json::json!(20));
+    assert!(matches!(
+        result,
+        Err(EditError::UpdateFailed(EntityUpdateError::TemplateValidationFailed(_)))
+    ));
+    assert!(!range.update());
+    assert!(!subscriber.can_undo());
+
+    subscriber.commit(range.group_id(), min_id, &serde_json::json!(5)).unwrap();
+    assert!(range.update());
+    assert_eq!(range.min, 5);
+}
+
+static FROZEN: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[derive(config_it::Template, Clone)]
+#[config(validate = "Frozen::validate")]
+struct Frozen {
+    #[config(default = 0)]
+    value: i32,
+}
+
+impl Frozen {
+    fn validate(&mut self) -> Result<config_it::Validation, std::borrow::Cow<'static, str>> {
+        if FROZEN.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err("frozen".into());
+        }
+
+        Ok(config_it::Validation::Valid)
+    }
+}
+
+#[test]
+fn failed_replay_keeps_record() {
+    use std::sync::atomic::Ordering;
+
+    let storage = config_it::create_storage();
+    let subscriber = Subscriber::new(&storage);
+    let mut frozen = storage.create::<Frozen>(["frozen"]).unwrap().updated();
+    let value_id = subscriber.with_group(frozen.group_id(), |g| g.items[0].item_id).unwrap();
+
+    subscriber.commit(frozen.group_id(), value_id, &serde_json::json!(1)).unwrap();
+
+    FROZEN.store(true, Ordering::Relaxed);
+    assert!(subscriber.undo().is_err());
+    assert!(subscriber.can_undo() && !subscriber.can_redo());
+
+    FROZEN.store(false, Ordering::Relaxed);
+    subscriber.undo().unwrap().unwrap();
+    assert!(frozen.update());
+    assert_eq!(frozen.value, 0);
+
+    FROZEN.store(true, Ordering::Relaxed);
+    assert!(subscriber.redo().is_err());
+    assert!(subscriber.can_redo() && !subscriber.can_undo());
+
+    FROZEN.store(false, Ordering::Relaxed);
+    subscriber.redo().unwrap().unwrap();
+    assert!(frozen.update());
+    assert_eq!(frozen.value, 1);
+}